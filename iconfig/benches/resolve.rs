@@ -0,0 +1,54 @@
+//! Compares `resolve` (through a JSON string) with `resolve_streaming` (straight from the TOML tree).
+//! Run with `cargo bench -p iconfig --bench resolve`.
+
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+
+use iconfig::ApplicationConfig;
+use serde::Deserialize;
+
+#[derive(Debug, PartialEq, Deserialize)]
+struct Service {
+    name: String,
+    port: i64,
+    enabled: bool,
+    tags: Vec<String>,
+}
+
+type Services = BTreeMap<String, Service>;
+
+const SERVICES: usize = 20_000;
+const ROUNDS: u32 = 10;
+
+fn config() -> ApplicationConfig {
+    let mut source = String::new();
+    for i in 0..SERVICES {
+        source.push_str(&format!(
+            "[service_{}]\nname = \"svc-{}\"\nport = {}\nenabled = {}\ntags = [\"a\", \"b\"]\n",
+            i,
+            i,
+            i,
+            i % 2 == 0
+        ));
+    }
+    ApplicationConfig::from_str(&source).unwrap()
+}
+
+fn time(rounds: u32, f: impl Fn() -> Services) -> Duration {
+    let started = Instant::now();
+    for _ in 0..rounds {
+        assert_eq!(f().len(), SERVICES);
+    }
+    started.elapsed() / rounds
+}
+
+fn main() {
+    let config = config();
+    let json = config.jsonify().unwrap();
+    println!("{} services, {} KiB of intermediate JSON", SERVICES, json.len() / 1024);
+
+    let resolve = time(ROUNDS, || config.resolve().unwrap());
+    let streaming = time(ROUNDS, || config.resolve_streaming().unwrap());
+    println!("resolve:           {:?} per round", resolve);
+    println!("resolve_streaming: {:?} per round", streaming);
+}
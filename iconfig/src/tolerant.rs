@@ -0,0 +1,433 @@
+//! A deserializer over the TOML tree that keeps going after field errors.
+//!
+//! Each failing field is reported with its dotted path, then replaced by a placeholder
+//! (zero, empty string, empty collection, ...) and deserialization starts over,
+//! until it succeeds or an error repeats at a path that was already replaced.
+//! [`deserialize`] makes a single strict pass instead, stopping at the first error.
+
+use std::cell::RefCell;
+use std::collections::BTreeSet;
+use std::fmt;
+
+use serde::de::value::{MapAccessDeserializer, MapDeserializer, SeqDeserializer};
+use serde::de::{
+    self, DeserializeOwned, DeserializeSeed, EnumAccess, IntoDeserializer, MapAccess, SeqAccess,
+    VariantAccess, Visitor,
+};
+use serde::forward_to_deserialize_any;
+use toml::Value;
+
+use crate::{join_path, FieldError};
+
+// Deserializes straight from the borrowed tree without copying it.
+// Paths are only tracked to report an error, in a second pass over the failing tree.
+pub(crate) fn deserialize<T: DeserializeOwned>(value: &Value) -> Result<T, FieldError> {
+    let untracked = State { untracked: true, ..State::default() };
+    let first = match T::deserialize(Tolerant { value, path: String::new(), state: &untracked }) {
+        Ok(typed) => return Ok(typed),
+        Err(error) => error,
+    };
+    let state = State::default();
+    let error = T::deserialize(Tolerant { value, path: String::new(), state: &state }).err().unwrap_or(first);
+    let (path, _) = state.failure_path(&error);
+    Err(FieldError { path, message: error.to_string() })
+}
+
+pub(crate) fn deserialize_all<T: DeserializeOwned>(value: &Value) -> Result<T, Vec<FieldError>> {
+    let state = State::default();
+    let mut errors = Vec::new();
+    loop {
+        let result = T::deserialize(Tolerant { value, path: String::new(), state: &state });
+        let error = match result {
+            Ok(typed) if errors.is_empty() => return Ok(typed),
+            Ok(_) => return Err(errors),
+            Err(error) => error,
+        };
+        let (path, is_key) = state.failure_path(&error);
+        let progressed = match is_key {
+            true => state.skipped_keys.borrow_mut().insert(path.clone()),
+            false => state.replaced.borrow_mut().insert(path.clone()),
+        };
+        if progressed {
+            errors.push(FieldError { path: path.clone(), message: error.to_string() });
+        }
+        // An error at the root cannot be replaced, so there is nothing more to find
+        if !progressed || path.is_empty() {
+            return Err(errors);
+        }
+    }
+}
+
+#[derive(Default)]
+struct State {
+    // Skips building paths, leaving every path empty and nothing replaced or skipped
+    untracked: bool,
+    // Paths whose value is replaced by a placeholder
+    replaced: RefCell<BTreeSet<String>>,
+    // Paths whose key is left out of its table
+    skipped_keys: RefCell<BTreeSet<String>>,
+    // Innermost path of the last error and whether it came from a key
+    failure: RefCell<Option<(String, bool)>>,
+}
+
+impl State {
+    fn fail(&self, path: String, is_key: bool) {
+        self.failure.borrow_mut().get_or_insert((path, is_key));
+    }
+
+    // Takes the path of the last failure, pointing at the missing field itself for `Error::Missing`.
+    fn failure_path(&self, error: &Error) -> (String, bool) {
+        let (path, is_key) = self.failure.borrow_mut().take().unwrap_or_default();
+        match error {
+            Error::Missing(field) => (join_path(&path, field), is_key),
+            Error::Custom(_) => (path, is_key),
+        }
+    }
+
+    fn join(&self, prefix: &str, key: &str) -> String {
+        match self.untracked {
+            true => String::new(),
+            false => join_path(prefix, key),
+        }
+    }
+
+    fn is_replaced(&self, path: &str) -> bool {
+        !self.untracked && self.replaced.borrow().contains(path)
+    }
+}
+
+#[derive(Debug)]
+enum Error {
+    Missing(&'static str),
+    Custom(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Missing(field) => write!(f, "missing field `{}`", field),
+            Error::Custom(message) => f.write_str(message),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl de::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Error::Custom(msg.to_string())
+    }
+
+    fn missing_field(field: &'static str) -> Self {
+        Error::Missing(field)
+    }
+}
+
+struct Tolerant<'a> {
+    value: &'a Value,
+    path: String,
+    state: &'a State,
+}
+
+impl<'de> de::Deserializer<'de> for Tolerant<'_> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.value {
+            Value::String(s) => visitor.visit_str(s),
+            Value::Integer(i) => visitor.visit_i64(*i),
+            Value::Float(f) => visitor.visit_f64(*f),
+            Value::Boolean(b) => visitor.visit_bool(*b),
+            Value::Datetime(d) => visitor.visit_string(d.to_string()),
+            Value::Array(items) => visitor.visit_seq(ArrayAccess {
+                items: items.iter().enumerate(),
+                path: self.path,
+                state: self.state,
+            }),
+            Value::Table(table) => visitor.visit_map(TableAccess::new(table, &[], self.path, self.state)),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        match self.value {
+            Value::Table(table) => visitor.visit_map(TableAccess::new(table, fields, self.path, self.state)),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        match self.value {
+            Value::String(s) => visitor.visit_enum(s.as_str().into_deserializer()),
+            Value::Table(table) => visitor.visit_enum(MapAccessDeserializer::new(TableAccess::new(
+                table,
+                &[],
+                self.path,
+                self.state,
+            ))),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct seq tuple tuple_struct map identifier ignored_any
+    }
+}
+
+struct TableAccess<'a> {
+    // Entries without a value are replaced fields missing from the table
+    entries: std::vec::IntoIter<(&'a str, Option<&'a Value>)>,
+    value: Option<(&'a str, Option<&'a Value>)>,
+    path: String,
+    state: &'a State,
+}
+
+impl<'a> TableAccess<'a> {
+    fn new(table: &'a toml::Table, fields: &'static [&'static str], path: String, state: &'a State) -> Self {
+        let skipped = state.skipped_keys.borrow();
+        let mut entries = table
+            .iter()
+            .filter(|(key, _)| state.untracked || !skipped.contains(&join_path(&path, key)))
+            .map(|(key, value)| (key.as_str(), Some(value)))
+            .collect::<Vec<_>>();
+        entries.extend(
+            fields
+                .iter()
+                .filter(|field| !state.untracked && !table.contains_key(**field) && state.is_replaced(&join_path(&path, field)))
+                .map(|field| (*field, None)),
+        );
+        drop(skipped);
+        TableAccess { entries: entries.into_iter(), value: None, path, state }
+    }
+}
+
+impl<'de> MapAccess<'de> for TableAccess<'_> {
+    type Error = Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>, Error> {
+        let Some((key, value)) = self.entries.next() else {
+            return Ok(None);
+        };
+        self.value = Some((key, value));
+        seed.deserialize(key.into_deserializer())
+            .map(Some)
+            .map_err(|e| {
+                self.state.fail(self.state.join(&self.path, key), true);
+                e
+            })
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, Error> {
+        let (key, value) = self.value.take().ok_or_else(|| de::Error::custom("value requested before key"))?;
+        let path = self.state.join(&self.path, key);
+        match value {
+            Some(value) if !self.state.is_replaced(&path) => {
+                let state = self.state;
+                seed.deserialize(Tolerant { value, path: path.clone(), state }).map_err(|e| {
+                    state.fail(path, false);
+                    e
+                })
+            }
+            _ => seed.deserialize(Placeholder),
+        }
+    }
+}
+
+struct ArrayAccess<'a> {
+    items: std::iter::Enumerate<std::slice::Iter<'a, Value>>,
+    path: String,
+    state: &'a State,
+}
+
+impl<'de> SeqAccess<'de> for ArrayAccess<'_> {
+    type Error = Error;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(&mut self, seed: T) -> Result<Option<T::Value>, Error> {
+        let Some((index, value)) = self.items.next() else {
+            return Ok(None);
+        };
+        let path = match self.state.untracked {
+            true => String::new(),
+            false => join_path(&self.path, &index.to_string()),
+        };
+        if self.state.is_replaced(&path) {
+            return seed.deserialize(Placeholder).map(Some);
+        }
+        let state = self.state;
+        seed.deserialize(Tolerant { value, path: path.clone(), state })
+            .map(Some)
+            .map_err(|e| {
+                state.fail(path, false);
+                e
+            })
+    }
+}
+
+macro_rules! zero_values {
+    ($($method:ident => $visit:ident($value:expr)),*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+                visitor.$visit($value)
+            }
+        )*
+    };
+}
+
+// Stands in for a failed value: answers every request with the zero value of the requested type.
+#[derive(Clone, Copy)]
+struct Placeholder;
+
+impl<'de> IntoDeserializer<'de, Error> for Placeholder {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+impl<'de> de::Deserializer<'de> for Placeholder {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_unit()
+    }
+
+    zero_values! {
+        deserialize_bool => visit_bool(false),
+        deserialize_i8 => visit_i8(0),
+        deserialize_i16 => visit_i16(0),
+        deserialize_i32 => visit_i32(0),
+        deserialize_i64 => visit_i64(0),
+        deserialize_i128 => visit_i128(0),
+        deserialize_u8 => visit_u8(0),
+        deserialize_u16 => visit_u16(0),
+        deserialize_u32 => visit_u32(0),
+        deserialize_u64 => visit_u64(0),
+        deserialize_u128 => visit_u128(0),
+        deserialize_f32 => visit_f32(0.0),
+        deserialize_f64 => visit_f64(0.0),
+        deserialize_char => visit_char('\0'),
+        deserialize_str => visit_str(""),
+        deserialize_string => visit_str(""),
+        deserialize_identifier => visit_str(""),
+        deserialize_bytes => visit_bytes(&[]),
+        deserialize_byte_buf => visit_bytes(&[])
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_none()
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(self, len: usize, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_seq(SeqDeserializer::new(std::iter::repeat(self).take(len)))
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.deserialize_tuple(len, visitor)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_tuple(0, visitor)
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_map(MapDeserializer::new(std::iter::empty::<(&str, Placeholder)>()))
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_map(MapDeserializer::new(fields.iter().map(|field| (*field, self))))
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        match variants.first() {
+            Some(variant) => visitor.visit_enum(PlaceholderVariant(variant)),
+            None => Err(de::Error::custom("enum has no variants")),
+        }
+    }
+
+    forward_to_deserialize_any! {
+        unit unit_struct ignored_any
+    }
+}
+
+// Picks the first variant of an enum, filled with placeholders.
+struct PlaceholderVariant(&'static str);
+
+impl<'de> EnumAccess<'de> for PlaceholderVariant {
+    type Error = Error;
+    type Variant = Placeholder;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(self, seed: V) -> Result<(V::Value, Placeholder), Error> {
+        let variant = seed.deserialize(self.0.into_deserializer())?;
+        Ok((variant, Placeholder))
+    }
+}
+
+impl<'de> VariantAccess<'de> for Placeholder {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value, Error> {
+        seed.deserialize(self)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, len: usize, visitor: V) -> Result<V::Value, Error> {
+        de::Deserializer::deserialize_tuple(self, len, visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        de::Deserializer::deserialize_struct(self, "", fields, visitor)
+    }
+}
@@ -0,0 +1,41 @@
+use iconfig::{load, load_from, load_with_report, CONFIG_PATH_ENV, PROFILE_ENV};
+
+// The only test in this binary, so setting the process environment cannot race another test.
+#[test]
+fn load_should_apply_profiles_from_config_path_env() {
+    // Given
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("config.toml");
+    std::fs::write(&path, r#"
+        active_profile = "prod"
+        [server]
+        host = "localhost"
+        port = 8080
+        [profiles.prod.server]
+        host = "api.example.com"
+        [profiles.dev.server]
+        port = 3000
+    "#).unwrap();
+    std::env::set_var(CONFIG_PATH_ENV, &path);
+    // When
+    let config = load().unwrap();
+    // Then
+    assert_eq!(config.get_string("server.host").as_deref(), Some("api.example.com"));
+    assert_eq!(config.get_i64("server.port"), Some(8080));
+    assert!(config.get("profiles").is_none());
+
+    // Given
+    std::env::set_var(PROFILE_ENV, "dev");
+    // When
+    let config = load().unwrap();
+    let (reported, report) = load_with_report();
+    let explicit = load_from(&[&path]).unwrap();
+    // Then
+    assert_eq!(config.get_string("server.host").as_deref(), Some("localhost"));
+    assert_eq!(config.get_i64("server.port"), Some(3000));
+    assert_eq!(reported.get_i64("server.port"), Some(3000));
+    assert_eq!(report.profile_error, None);
+    // load_from ignores the environment and applies only active_profile
+    assert_eq!(explicit.get_string("server.host").as_deref(), Some("api.example.com"));
+    assert_eq!(explicit.get_i64("server.port"), Some(8080));
+}
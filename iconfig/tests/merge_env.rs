@@ -0,0 +1,17 @@
+use iconfig::{ApplicationConfig, CONFIG_PATH_ENV, PROFILE_ENV};
+
+// The only test in this binary: it sets the loader's own variables, which load() reads.
+#[test]
+fn merge_env_should_skip_loader_variables_under_rioc_prefix() {
+    // Given
+    let mut config = ApplicationConfig::from_str("[server]\nport = 80").unwrap();
+    std::env::set_var(CONFIG_PATH_ENV, "/srv/app.toml");
+    std::env::set_var(PROFILE_ENV, "prod");
+    std::env::set_var("RIOC_SERVER__PORT", "7070");
+    // When
+    config.merge_env("RIOC").unwrap();
+    // Then
+    assert_eq!(config.get("server.port").unwrap().as_integer(), Some(7070));
+    assert!(config.get("config_path").is_none());
+    assert!(config.get("profile").is_none());
+}
@@ -0,0 +1,76 @@
+use iconfig::{ApplicationConfig, ResolveSwitch};
+use rioc::provider;
+use std::sync::Arc;
+
+trait Cache {
+    fn backend(&self) -> String;
+}
+
+struct MemoryCache;
+
+impl Cache for MemoryCache {
+    fn backend(&self) -> String {
+        "memory".to_string()
+    }
+}
+
+struct RedisCache {
+    url: String,
+}
+
+impl Cache for RedisCache {
+    fn backend(&self) -> String {
+        format!("redis at {}", self.url)
+    }
+}
+
+#[provider]
+#[provide(Arc<ApplicationConfig>, self.config.clone())]
+struct AppProvider {
+    config: Arc<ApplicationConfig>,
+}
+
+impl AppProvider {
+    fn with_config(source: &str) -> Self {
+        Self {
+            config: Arc::new(ApplicationConfig::from_str(source).unwrap()),
+        }
+    }
+
+    fn cache(&self) -> Result<Box<dyn Cache>, anyhow::Error> {
+        self.resolve_switch(
+            "cache.backend",
+            &[
+                ("memory", |_| Box::new(MemoryCache) as Box<dyn Cache>),
+                ("redis", |p| {
+                    let config: Arc<ApplicationConfig> = p.provide();
+                    let url = config.get("cache.url").and_then(|v| v.as_str()).unwrap_or_default();
+                    Box::new(RedisCache { url: url.to_string() }) as Box<dyn Cache>
+                }),
+            ],
+        )
+    }
+}
+
+#[test]
+fn resolve_switch_should_follow_the_config_value() {
+    // Given
+    let memory = AppProvider::with_config("[cache]\nbackend = \"memory\"");
+    let redis = AppProvider::with_config("[cache]\nbackend = \"redis\"\nurl = \"redis://localhost\"");
+    // When
+    let from_memory = memory.cache().unwrap();
+    let from_redis = redis.cache().unwrap();
+    // Then
+    assert_eq!(from_memory.backend(), "memory");
+    assert_eq!(from_redis.backend(), "redis at redis://localhost");
+}
+
+#[test]
+fn resolve_switch_with_unknown_value_should_error() {
+    // Given
+    let provider = AppProvider::with_config("[cache]\nbackend = \"disk\"");
+    // When
+    let err = provider.cache().err().unwrap();
+    // Then
+    assert!(err.to_string().contains("\"disk\""));
+}
@@ -0,0 +1,69 @@
+use crate::core::error;
+use proc_macro::TokenStream;
+use quote::quote;
+use syn::{
+    parse::{Parse, ParseStream},
+    spanned::Spanned,
+    Expr, ItemStruct, LitStr, Token,
+};
+
+struct ConfigDefault {
+    path: LitStr,
+    value: Expr,
+}
+impl Parse for ConfigDefault {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let path: LitStr = input.parse()?;
+        if path.value().is_empty() {
+            return Err(syn::Error::new(path.span(), "Config path must not be empty."));
+        }
+        input.parse::<Token![,]>()?;
+        Ok(Self {
+            path,
+            value: input.parse()?,
+        })
+    }
+}
+
+pub(crate) fn handle_config_default(
+    attr: TokenStream,
+    item: TokenStream,
+) -> syn::Result<TokenStream> {
+    let mut input = syn::parse::<ItemStruct>(item)?;
+    let mut defaults = vec![syn::parse::<ConfigDefault>(attr).map_err(|e| {
+        error::combine(
+            syn::Error::new(e.span(), "Unable to parse config_default attribute."),
+            e,
+        )
+    })?];
+    // The outermost attribute collects the remaining ones so a single impl is generated.
+    let mut remaining = Vec::with_capacity(input.attrs.len());
+    for attr in input.attrs.drain(..) {
+        if attr.path().is_ident("config_default") {
+            defaults.push(attr.parse_args::<ConfigDefault>().map_err(|e| {
+                error::combine(
+                    syn::Error::new(attr.span(), "Unable to parse config_default attribute."),
+                    e,
+                )
+            })?);
+        } else {
+            remaining.push(attr);
+        }
+    }
+    input.attrs = remaining;
+
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let paths = defaults.iter().map(|d| &d.path);
+    let values = defaults.iter().map(|d| &d.value);
+    let output = quote! {
+        #input
+
+        impl #impl_generics iconfig::ConfigDefaults for #ident #ty_generics #where_clause {
+            fn config_defaults() -> Vec<(&'static str, iconfig::toml::Value)> {
+                vec![#((#paths, iconfig::toml::Value::from(#values))),*]
+            }
+        }
+    };
+    Ok(output.into())
+}
@@ -3,27 +3,173 @@
 
 use crate::core::{error, DeriveInput, FactoryExpr};
 use proc_macro::TokenStream;
-use quote::quote;
+use quote::{format_ident, quote, quote_spanned};
 use syn::{
     parse::{Parse, ParseStream},
     spanned::Spanned,
-    Expr, PatType, Token,
+    Expr, GenericArgument, PatType, PathArguments, ReturnType, Token, Type, TypeParamBound,
 };
 
-struct InjectExpr(Box<Expr>, Vec<PatType>);
+enum InjectExpr {
+    Expr(Box<Expr>, Vec<PatType>),
+    // `#[inject(factory)]` on a `Box<dyn Fn() -> T>` field.
+    Factory,
+}
 impl Parse for InjectExpr {
     fn parse(input: ParseStream) -> syn::Result<Self> {
         if input.peek(Token![|]) {
             let expr = FactoryExpr::parse(input)?;
-            Ok(InjectExpr(expr.body, expr.inputs))
-        } else {
-            Ok(InjectExpr(input.parse()?, vec![]))
+            return Ok(InjectExpr::Expr(expr.body, expr.inputs));
+        }
+        let expr: Expr = input.parse()?;
+        match &expr {
+            Expr::Path(p) if p.qself.is_none() && p.path.is_ident("factory") => {
+                Ok(InjectExpr::Factory)
+            }
+            _ => Ok(InjectExpr::Expr(Box::new(expr), vec![])),
+        }
+    }
+}
+
+// Extracts `T` out of a `Box<dyn Fn() -> T>` field type.
+fn factory_output_type(ty: &Type) -> Option<&Type> {
+    let Type::Path(path) = ty else {
+        return None;
+    };
+    let segment = path.path.segments.last().filter(|s| s.ident == "Box")?;
+    let PathArguments::AngleBracketed(args) = &segment.arguments else {
+        return None;
+    };
+    let Some(GenericArgument::Type(Type::TraitObject(object))) = args.args.first() else {
+        return None;
+    };
+    object.bounds.iter().find_map(|b| {
+        let TypeParamBound::Trait(bound) = b else {
+            return None;
+        };
+        let segment = bound.path.segments.last().filter(|s| s.ident == "Fn")?;
+        match &segment.arguments {
+            PathArguments::Parenthesized(args) if args.inputs.is_empty() => match &args.output {
+                ReturnType::Type(_, t) => Some(t.as_ref()),
+                ReturnType::Default => None,
+            },
+            _ => None,
+        }
+    })
+}
+
+// Generates the expression creating a field annotated with `#[inject(...)]`.
+fn gen_inject_output(attr: &InjectExpr, ty: &Type) -> proc_macro2::TokenStream {
+    match attr {
+        InjectExpr::Expr(output, inputs) if inputs.is_empty() => quote! { #output },
+        InjectExpr::Expr(output, inputs) => quote! {
+            {
+                #(let #inputs = provider.provide();)*
+                #output
+            }
+        },
+        InjectExpr::Factory => {
+            let output = factory_output_type(ty);
+            quote! { Box::new(move || rioc::Provider::<'prov, #output>::provide(provider)) }
         }
     }
 }
 
+// Like `gen_inject_output`, but calls the check fn of already checked expressions.
+fn gen_field_output(attr: &InjectExpr, ty: &Type, index: usize, checked: bool) -> proc_macro2::TokenStream {
+    match checked_inject_expr(attr) {
+        Some(_) if checked => {
+            let name = check_fn_ident(index);
+            quote! { Self::#name() }
+        }
+        _ => gen_inject_output(attr, ty),
+    }
+}
+
 pub(crate) fn handle_injectable(item: TokenStream) -> syn::Result<TokenStream> {
     let input = syn::parse::<DeriveInput>(item)?;
+    let injectable_output = gen_injectable_impl(&input, has_inject_checks(&input))?;
+    let output = quote! {
+        #[derive(rioc::InjectableHelperAttr)]
+        #input
+
+        #injectable_output
+    };
+    Ok(output.into())
+}
+
+// Name of the associated fn type-checking the `#[inject(expr)]` of the field at `index`.
+fn check_fn_ident(index: usize) -> proc_macro2::Ident {
+    format_ident!("__rioc_check_inject_{}", index)
+}
+
+// Whether plain `#[inject(expr)]` attributes of the struct get a check fn.
+// Expressions on generic structs may rely on bounds that only the provider supplies.
+fn has_inject_checks(input: &DeriveInput) -> bool {
+    let generics = &input.generics;
+    generics.type_params().next().is_none()
+        && generics.const_params().next().is_none()
+        && generics.where_clause.is_none()
+}
+
+// The plain expression of a field checked by `handle_injectable_helper_attr`, if any.
+fn checked_inject_expr(attr: &InjectExpr) -> Option<&Expr> {
+    match attr {
+        InjectExpr::Expr(expr, inputs) if inputs.is_empty() => Some(expr),
+        _ => None,
+    }
+}
+
+// Type-checks every plain `#[inject(expr)]` against its field type in an associated fn of the struct,
+// so a mismatch is reported at the expression instead of deep inside the generated `inject`,
+// which calls the check fn instead of repeating the expression.
+pub(crate) fn handle_injectable_helper_attr(item: TokenStream) -> syn::Result<TokenStream> {
+    let input = syn::parse::<DeriveInput>(item)?;
+    if !has_inject_checks(&input) {
+        return Ok(TokenStream::new());
+    }
+    let ident = &input.ident;
+    let lifetime_params = input.generics.lifetimes().collect::<Vec<_>>();
+    let lifetimes = lifetime_params.iter().map(|l| &l.lifetime);
+    let checks = input
+        .fields()
+        .iter()
+        .enumerate()
+        .filter_map(|(index, f)| {
+            let attr = f.attrs.iter().rev().find(|a| a.path().is_ident("inject"))?;
+            // Parse errors and factories are reported by `#[injectable]` itself.
+            let attr = attr.parse_args::<InjectExpr>().ok()?;
+            let expr = checked_inject_expr(&attr)?;
+            let ty = &f.ty;
+            let name = check_fn_ident(index);
+            Some(quote_spanned! {expr.span()=>
+                #[allow(dead_code, clippy::all)]
+                #[doc(hidden)]
+                fn #name() -> #ty {
+                    #expr
+                }
+            })
+        })
+        .collect::<Vec<_>>();
+    if checks.is_empty() {
+        return Ok(TokenStream::new());
+    }
+    let output = quote! {
+        impl<#(#lifetime_params),*> #ident<#(#lifetimes),*> {
+            #(#checks)*
+        }
+    };
+    Ok(output.into())
+}
+
+pub(crate) fn handle_injectable_derive(item: TokenStream) -> syn::Result<TokenStream> {
+    let input = syn::parse::<DeriveInput>(item)?;
+    let output = gen_injectable_impl(&input, false)?;
+    Ok(output.into())
+}
+
+// `checked` tells that `InjectableHelperAttr` generated the check fns for plain expressions.
+fn gen_injectable_impl(input: &DeriveInput, checked: bool) -> syn::Result<proc_macro2::TokenStream> {
     let ident = &input.ident;
     let fields = input.fields();
     let types = input.field_types();
@@ -39,12 +185,19 @@ pub(crate) fn handle_injectable(item: TokenStream) -> syn::Result<TokenStream> {
             else {
                 return Ok(None);
             };
-            attr.parse_args::<InjectExpr>().map(Some).map_err(|e| {
+            let expr = attr.parse_args::<InjectExpr>().map_err(|e| {
                 error::combine(
                     syn::Error::new(attr.span(), "Unable to parse inject attribute"),
                     e,
                 )
-            })
+            })?;
+            if matches!(expr, InjectExpr::Factory) && factory_output_type(&f.ty).is_none() {
+                return Err(syn::Error::new(
+                    f.ty.span(),
+                    "Factory injection requires a `Box<dyn Fn() -> T>` field",
+                ));
+            }
+            Ok(Some(expr))
         })
         .collect::<syn::Result<Vec<_>>>()?;
     let generic_params = input.generic_params();
@@ -63,44 +216,17 @@ pub(crate) fn handle_injectable(item: TokenStream) -> syn::Result<TokenStream> {
     };
     let creation_output = match keys.is_empty() && !types.is_empty() {
         true => {
-            let items = types.iter().zip(&attributes).map(|(_, a)| match a {
-                Some(attr) => {
-                    let inputs = attr
-                        .1
-                        .iter()
-                        .map(|x| quote! { let #x = provider.provide(); })
-                        .collect::<Vec<_>>();
-                    let output = &attr.0;
-                    if inputs.is_empty() {
-                        quote! { #output }
-                    } else {
-                        quote! {
-                            {
-                                #(#inputs)*
-                                #output
-                            }
-                        }
-                    }
-                }
+            let items = types.iter().zip(&attributes).enumerate().map(|(i, (t, a))| match a {
+                Some(attr) => gen_field_output(attr, t, i, checked),
                 None => quote! { provider.provide() },
             });
             quote! { #ident(#(#items),*) }
         }
         false => {
-            let items = keys.iter().zip(&attributes).map(|(k, a)| match a {
+            let items = keys.iter().zip(types.iter().zip(&attributes)).enumerate().map(|(i, (k, (t, a)))| match a {
                 Some(attr) => {
-                    let inputs = attr
-                        .1
-                        .iter()
-                        .map(|x| quote! { let #x = provider.provide(); })
-                        .collect::<Vec<_>>();
-                    let output = &attr.0;
-                    quote! {
-                        #k: {
-                            #(#inputs)*
-                            #output
-                        }
-                    }
+                    let output = gen_field_output(attr, t, i, checked);
+                    quote! { #k: #output }
                 }
                 None => quote! { #k: provider.provide() },
             });
@@ -109,19 +235,21 @@ pub(crate) fn handle_injectable(item: TokenStream) -> syn::Result<TokenStream> {
     };
     let mut prov_types = Vec::<_>::with_capacity(types.len());
     for (t, a) in types.iter().zip(&attributes) {
-        if let Some(attr) = a {
-            for attr_type in attr.1.iter().map(|x| &x.ty) {
-                prov_types.push(quote! {#attr_type});
+        match a {
+            Some(InjectExpr::Expr(_, inputs)) => {
+                for attr_type in inputs.iter().map(|x| &x.ty) {
+                    prov_types.push(quote! {#attr_type});
+                }
             }
-        } else {
-            prov_types.push(quote! {#t});
+            Some(InjectExpr::Factory) => {
+                let output = factory_output_type(t);
+                prov_types.push(quote! {#output});
+            }
+            None => prov_types.push(quote! {#t}),
         }
     }
     prov_types.dedup_by(|a, b| a.to_string() == b.to_string());
     let output = quote! {
-        #[derive(rioc::InjectableHelperAttr)]
-        #input
-
         impl<'prov, #(#generic_params,)*NjectProvider> rioc::Injectable<'prov, #ident<#(#generic_keys),*>, NjectProvider> for #ident<#(#generic_keys),*>
             where
                 #prov_lifetimes
@@ -133,5 +261,5 @@ pub(crate) fn handle_injectable(item: TokenStream) -> syn::Result<TokenStream> {
             }
         }
     };
-    Ok(output.into())
+    Ok(output)
 }
\ No newline at end of file
@@ -1,24 +1,28 @@
 #![allow(clippy::needless_doctest_main)]
+mod config_default;
 mod core;
 mod inject;
 mod injectable;
 mod module;
+mod protocol_layer;
 mod provider;
 mod verbatim;
 mod parse;
 mod expand;
+use config_default::handle_config_default;
 use inject::handle_inject;
-use injectable::handle_injectable;
+use injectable::{handle_injectable, handle_injectable_derive, handle_injectable_helper_attr};
 use module::handle_module;
 use parse::TraitImpl;
 use proc_macro::TokenStream;
+use protocol_layer::handle_protocol_layer;
 use provider::handle_provider;
 use syn::{parse::Nothing, parse_macro_input};
 
 /// For internal purposes only. Should not be used.
 #[proc_macro_derive(InjectableHelperAttr, attributes(inject))]
-pub fn injectable_helper_attr(_item: TokenStream) -> TokenStream {
-    TokenStream::new()
+pub fn injectable_helper_attr(item: TokenStream) -> TokenStream {
+    handle_injectable_helper_attr(item).unwrap_or_else(|e| e.to_compile_error().into())
 }
 
 /// For internal purposes only. Should not be used.
@@ -28,7 +32,7 @@ pub fn module_helper_attr(_item: TokenStream) -> TokenStream {
 }
 
 /// For internal purposes only. Should not be used.
-#[proc_macro_derive(ProviderHelperAttr, attributes(import, provide, scope))]
+#[proc_macro_derive(ProviderHelperAttr, attributes(import, provide, scope, provider_skip))]
 pub fn provider_helper_attr(_item: TokenStream) -> TokenStream {
     TokenStream::new()
 }
@@ -56,6 +60,60 @@ pub fn injectable(_attr: TokenStream, item: TokenStream) -> TokenStream {
     handle_injectable(item).unwrap_or_else(|e| e.to_compile_error().into())
 }
 
+/// Implement `Injectable` for a plain struct without rewriting it.
+/// Each field is provided by the provider unless overridden with `#[inject(...)]`.
+/// ```rust
+/// use imacro::{provider, Injectable};
+///
+/// #[derive(Injectable)]
+/// struct Facade {
+///     dep: i32,
+///     #[inject(String::from("facade"))]
+///     name: String,
+/// }
+///
+/// #[provider]
+/// #[provide(i32, 42)]
+/// struct Provider;
+///
+/// let facade: Facade = Provider.provide();
+/// ```
+#[proc_macro_derive(Injectable, attributes(inject))]
+pub fn injectable_derive(item: TokenStream) -> TokenStream {
+    handle_injectable_derive(item).unwrap_or_else(|e| e.to_compile_error().into())
+}
+
+/// Generate `into_layer(self) -> rioc::SharedLayer` for a struct handling both directions
+/// with `fn inbound(&self, PayLoad)` and `fn outbound(&self, PayLoad)`.
+/// The handlers may return any error convertible into `LayerError`, such as `String`.
+/// A missing payload is passed to the handlers as `PayLoad::default()`.
+/// ```rust
+/// use rioc::{Direction, LayerChain, LayerResult, PayLoad, ProtocolLayer};
+///
+/// #[derive(ProtocolLayer)]
+/// struct Upper;
+///
+/// impl Upper {
+///     fn inbound(&self, req: PayLoad) -> Result<LayerResult, String> {
+///         let data = req.data.map(|d| d.to_uppercase());
+///         Ok(LayerResult { direction: Direction::Inbound, data: Some(PayLoad { data, ..req }) })
+///     }
+///
+///     fn outbound(&self, req: PayLoad) -> Result<LayerResult, String> {
+///         Ok(LayerResult { direction: Direction::Outbound, data: Some(req) })
+///     }
+/// }
+///
+/// let mut chain = LayerChain::new();
+/// chain.add_layer(Upper.into_layer());
+/// let result = chain.handle_inbound(Some(PayLoad::text("hi"))).unwrap();
+/// assert_eq!(result.data.unwrap().data.as_deref(), Some("HI"));
+/// ```
+#[proc_macro_derive(ProtocolLayer)]
+pub fn protocol_layer_derive(item: TokenStream) -> TokenStream {
+    handle_protocol_layer(item).unwrap_or_else(|e| e.to_compile_error().into())
+}
+
 /// Use the given value to inject.
 /// ```rust
 /// use rioc::{inject, injectable, provider};
@@ -107,6 +165,8 @@ pub fn inject(attr: TokenStream, item: TokenStream) -> TokenStream {
 /// let dependency: Dependency = provider.provide();
 /// let facade: Facade = provider.provide();
 /// ```
+///
+/// Fields marked with `#[provider_skip]` are left out of `#[import]` and `#[provide]` codegen.
 #[proc_macro_attribute]
 pub fn provider(_attr: TokenStream, item: TokenStream) -> TokenStream {
     handle_provider(item).unwrap_or_else(|e| e.to_compile_error().into())
@@ -166,6 +226,30 @@ pub fn module(attr: TokenStream, item: TokenStream) -> TokenStream {
     handle_module(attr, item).unwrap_or_else(|e| e.to_compile_error().into())
 }
 
+/// Declare default values for config keys, applied by `iconfig` when the config omits them.
+/// Paths are absolute dotted paths; the attribute can be repeated.
+/// ```rust
+/// use imacro::config_default;
+/// use iconfig::ApplicationConfig;
+/// use serde::Deserialize;
+///
+/// #[config_default("server.port", 8080)]
+/// #[config_default("server.host", "0.0.0.0")]
+/// #[derive(Deserialize)]
+/// struct Server {
+///     host: String,
+///     port: i64,
+/// }
+///
+/// let config = ApplicationConfig::from_str("[server]\nhost = \"localhost\"").unwrap();
+/// let server: Server = config.resolve_prefix_with_defaults("server").unwrap();
+/// assert_eq!((server.host.as_str(), server.port), ("localhost", 8080));
+/// ```
+#[proc_macro_attribute]
+pub fn config_default(attr: TokenStream, item: TokenStream) -> TokenStream {
+    handle_config_default(attr, item).unwrap_or_else(|e| e.to_compile_error().into())
+}
+
 #[proc_macro_attribute]
 pub fn inherent(args: TokenStream, input: TokenStream) -> TokenStream {
     parse_macro_input!(args as Nothing);
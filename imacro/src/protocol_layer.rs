@@ -0,0 +1,29 @@
+use proc_macro::TokenStream;
+use quote::quote;
+use syn::DeriveInput;
+
+pub(crate) fn handle_protocol_layer(item: TokenStream) -> syn::Result<TokenStream> {
+    let input = syn::parse::<DeriveInput>(item)?;
+    let ident = &input.ident;
+    let name = ident.to_string();
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let output = quote! {
+        impl #impl_generics #ident #ty_generics #where_clause {
+            /// Wrap this handler into a layer, `inbound` and `outbound` handling each direction.
+            pub fn into_layer(self) -> rioc::SharedLayer
+            where
+                Self: Send + Sync + 'static,
+            {
+                let inbound = std::sync::Arc::new(self);
+                let outbound = inbound.clone();
+                rioc::LayerBuilder::new()
+                    .with_name(#name)
+                    .with_inbound_fn(move |req| inbound.inbound(req.unwrap_or_default()).map_err(Into::into))
+                    .with_outbound_fn(move |req| outbound.outbound(req.unwrap_or_default()).map_err(Into::into))
+                    .build()
+                    .unwrap()
+            }
+        }
+    };
+    Ok(output.into())
+}
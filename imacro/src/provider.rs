@@ -9,6 +9,7 @@ use syn::{
     parse::{Parse, ParseStream},
     punctuated::Punctuated,
     spanned::Spanned,
+    visit_mut::{self, VisitMut},
     Expr, Field, GenericParam, Ident, Lifetime, LifetimeParam, PatType, Token, Type,
 };
 
@@ -31,6 +32,11 @@ impl Parse for ProvideStructInput {
 
 type ProvideFieldInput = FieldFactoryExpr;
 
+/// Fields marked with `#[provider_skip]` take no part in import or provide codegen.
+fn is_provider_skip(field: &Field) -> bool {
+    field.attrs.iter().any(|a| a.path().is_ident("provider_skip"))
+}
+
 pub(crate) fn handle_provider(
     item: proc_macro::TokenStream,
 ) -> syn::Result<proc_macro::TokenStream> {
@@ -49,6 +55,7 @@ pub(crate) fn handle_provider(
     let import_attr_indexes = fields
         .iter()
         .enumerate()
+        .filter(|(_, f)| !is_provider_skip(f))
         .filter_map(|(i, f)| {
             f.attrs
                 .iter()
@@ -60,6 +67,7 @@ pub(crate) fn handle_provider(
     let provide_attr_indexes = fields
         .iter()
         .enumerate()
+        .filter(|(_, f)| !is_provider_skip(f))
         .filter_map(|(i, f)| {
             let attrs = f
                 .attrs
@@ -110,6 +118,14 @@ pub(crate) fn handle_provider(
         &where_predicates,
         &provide_input_attr,
     );
+    let manifest_output = gen_manifest_output(
+        ident,
+        &generic_params,
+        &generic_keys,
+        &where_predicates,
+        &manifest_provides(&fields, &provide_attr_indexes, &provide_input_attr),
+        &manifest_imports(&fields, &import_attr_indexes),
+    );
     let scope_output = gen_scope_output(GenScopeOuptutInput {
         visibility: &input.vis,
         ident,
@@ -159,6 +175,8 @@ pub(crate) fn handle_provider(
         #(#provide_outputs)*
         #(#input_provide_outputs)*
 
+        #manifest_output
+
         #scope_output
     };
     Ok(output.into())
@@ -236,43 +254,19 @@ fn gen_providers_for_provide_attr_on_fields(
 ) -> Vec<proc_macro2::TokenStream> {
     let provide_outputs = provide_attr_indexes.iter().map(|(i, attrs)| {
         let field = fields[*i];
-        let (key_prefix, ref_prefix) = if let Type::Reference(r) = &field.ty {
-            let lifetime = &r.lifetime;
-            (quote! {}, quote! { &#lifetime })
-        } else {
-            (quote! { & }, quote! { &'prov })
+        let key_prefix = match &field.ty {
+            Type::Reference(_) => quote! {},
+            _ => quote! { & },
         };
-        let inputs = attrs.iter().map(|a| match a.meta {
-            syn::Meta::Path(_) => ProvideFieldInput::Type(field.ty.to_owned()),
-            _ => a.parse_args::<ProvideFieldInput>().unwrap()
-        });
+        let inputs = parse_provide_field_inputs(field, attrs);
         let index = syn::Index::from(*i);
         let field_key = match &field.ident {
             Some(i) => quote! { #i },
             None => quote! { #index },
         };
-        let outputs = inputs.map(|input| {
-            let ty = match &input {
-                ProvideFieldInput::None =>  match &field.ty {
-                    Type::Reference(r) => {
-                        let inner_ty = &r.elem;
-                        quote! { #ref_prefix #inner_ty }
-                    },
-                    _ => {
-                        let ty = &field.ty;
-                        quote! { #ref_prefix #ty }
-                    },
-                },
-                ProvideFieldInput::Type(t) => match t {
-                    Type::Reference(r) => {
-                        let inner_ty = &r.elem;
-                        quote! { #ref_prefix #inner_ty }
-                    },
-                    _ => quote! { #ref_prefix #t },
-                },
-                ProvideFieldInput::TypeExpr(t, _, _) => quote! { #t },
-            };
-            let body = match &input {
+        let outputs = inputs.iter().map(|input| {
+            let ty = provided_field_type(field, input);
+            let body = match input {
                 ProvideFieldInput::TypeExpr(_, i, e) => {
                     let ref_prefix = match & field.ty {
                         Type::Reference(_) => quote!{},
@@ -306,6 +300,43 @@ fn gen_providers_for_provide_attr_on_fields(
     provide_outputs.collect()
 }
 
+fn parse_provide_field_inputs(field: &Field, attrs: &[&syn::Attribute]) -> Vec<ProvideFieldInput> {
+    attrs
+        .iter()
+        .map(|a| match a.meta {
+            syn::Meta::Path(_) => ProvideFieldInput::Type(field.ty.to_owned()),
+            _ => a.parse_args::<ProvideFieldInput>().unwrap(),
+        })
+        .collect()
+}
+
+// Computes the type provided by a `#[provide]` attribute on a field.
+fn provided_field_type(field: &Field, input: &ProvideFieldInput) -> proc_macro2::TokenStream {
+    let ref_prefix = if let Type::Reference(r) = &field.ty {
+        let lifetime = &r.lifetime;
+        quote! { &#lifetime }
+    } else {
+        quote! { &'prov }
+    };
+    match input {
+        ProvideFieldInput::None => match &field.ty {
+            Type::Reference(r) => {
+                let inner_ty = &r.elem;
+                quote! { #ref_prefix #inner_ty }
+            }
+            ty => quote! { #ref_prefix #ty },
+        },
+        ProvideFieldInput::Type(t) => match t {
+            Type::Reference(r) => {
+                let inner_ty = &r.elem;
+                quote! { #ref_prefix #inner_ty }
+            }
+            _ => quote! { #ref_prefix #t },
+        },
+        ProvideFieldInput::TypeExpr(t, _, _) => quote! { #t },
+    }
+}
+
 pub(crate) fn gen_providers_for_provide_attr_on_struct(
     ident: &Ident,
     generic_params: &[&GenericParam],
@@ -337,6 +368,129 @@ pub(crate) fn gen_providers_for_provide_attr_on_struct(
     input_provide_outputs.collect()
 }
 
+// Lists the names of the types provided through `#[provide]`, struct attributes first.
+fn manifest_provides(
+    fields: &[&syn::Field],
+    provide_attr_indexes: &[(usize, Vec<&syn::Attribute>)],
+    provide_input_attr: &[&syn::Attribute],
+) -> Vec<String> {
+    let struct_provides = provide_input_attr
+        .iter()
+        .map(|a| match a.parse_args::<ProvideStructInput>().unwrap() {
+            ProvideStructInput::TypeExpr(t, _) => quote! { #t },
+            ProvideStructInput::TypeExprFact(t, _, _) => quote! { #t },
+        });
+    let field_provides = provide_attr_indexes.iter().flat_map(|(i, attrs)| {
+        let field = fields[*i];
+        parse_provide_field_inputs(field, attrs)
+            .iter()
+            .map(|input| provided_field_type(field, input))
+            .collect::<Vec<_>>()
+    });
+    struct_provides
+        .chain(field_provides)
+        .map(|ty| type_name(&ty))
+        .collect()
+}
+
+// Lists the names of the modules imported through `#[import]`.
+fn manifest_imports(fields: &[&syn::Field], import_attr_indexes: &[usize]) -> Vec<String> {
+    import_attr_indexes
+        .iter()
+        .map(|i| match &fields[*i].ty {
+            Type::Reference(r) => {
+                let inner_ty = &r.elem;
+                type_name(&quote! { #inner_ty })
+            }
+            ty => type_name(&quote! { #ty }),
+        })
+        .collect()
+}
+
+fn gen_manifest_output(
+    ident: &Ident,
+    generic_params: &[&GenericParam],
+    generic_keys: &[proc_macro2::TokenStream],
+    where_predicates: &proc_macro2::TokenStream,
+    provides: &[String],
+    imports: &[String],
+) -> proc_macro2::TokenStream {
+    quote! {
+
+        impl<#(#generic_params),*> rioc::ProviderManifest for #ident<#(#generic_keys),*>
+            where #where_predicates
+        {
+            fn provides() -> Vec<&'static str> {
+                vec![#(#provides),*]
+            }
+
+            fn imports() -> Vec<&'static str> {
+                vec![#(#imports),*]
+            }
+        }
+    }
+}
+
+// Renders a type as a string, keeping spaces only between words (e.g. `&dyn Greeter`).
+// Lifetimes are erased: they name the provider's generics, not anything tooling can use.
+fn type_name(ty: &proc_macro2::TokenStream) -> String {
+    let tokens = match syn::parse2::<Type>(ty.clone()) {
+        Ok(mut ty) => {
+            EraseLifetimes.visit_type_mut(&mut ty);
+            quote! { #ty }
+        }
+        Err(_) => ty.clone(),
+    };
+    let is_word = |c: char| c.is_alphanumeric() || c == '_';
+    let mut name = String::new();
+    for part in tokens.to_string().split_whitespace() {
+        let joins_words = name.ends_with(is_word) && part.starts_with(is_word);
+        if joins_words {
+            name.push(' ');
+        }
+        name.push_str(part);
+    }
+    name
+}
+
+struct EraseLifetimes;
+
+impl VisitMut for EraseLifetimes {
+    fn visit_type_reference_mut(&mut self, ty: &mut syn::TypeReference) {
+        ty.lifetime = None;
+        visit_mut::visit_type_reference_mut(self, ty);
+    }
+
+    fn visit_type_trait_object_mut(&mut self, ty: &mut syn::TypeTraitObject) {
+        ty.bounds = std::mem::take(&mut ty.bounds)
+            .into_iter()
+            .filter(|bound| !matches!(bound, syn::TypeParamBound::Lifetime(_)))
+            .collect();
+        visit_mut::visit_type_trait_object_mut(self, ty);
+    }
+
+    fn visit_type_impl_trait_mut(&mut self, ty: &mut syn::TypeImplTrait) {
+        ty.bounds = std::mem::take(&mut ty.bounds)
+            .into_iter()
+            .filter(|bound| !matches!(bound, syn::TypeParamBound::Lifetime(_)))
+            .collect();
+        visit_mut::visit_type_impl_trait_mut(self, ty);
+    }
+
+    fn visit_path_segment_mut(&mut self, segment: &mut syn::PathSegment) {
+        if let syn::PathArguments::AngleBracketed(generics) = &mut segment.arguments {
+            generics.args = std::mem::take(&mut generics.args)
+                .into_iter()
+                .filter(|arg| !matches!(arg, syn::GenericArgument::Lifetime(_)))
+                .collect();
+            if generics.args.is_empty() {
+                segment.arguments = syn::PathArguments::None;
+            }
+        }
+        visit_mut::visit_path_segment_mut(self, segment);
+    }
+}
+
 struct GenScopeOuptutInput<'a> {
     visibility: &'a syn::Visibility,
     ident: &'a Ident,
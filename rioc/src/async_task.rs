@@ -0,0 +1,130 @@
+use std::future::Future;
+
+use serde_json::Value;
+use tokio::runtime::Handle;
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+use tokio_util::sync::CancellationToken;
+
+use crate::function::panic_message;
+use crate::task::{CancelReason, TaskEvent};
+
+// 基于 tokio 的任务：和 JobTask 使用相同的 TaskEvent，但运行在调用方提供的运行时上
+pub struct AsyncJobTask<T, E, D> {
+    event_rx: UnboundedReceiver<TaskEvent<T, E>>,
+    sender: UnboundedSender<D>, // 用于向任务发送数据
+    token: CancellationToken,
+}
+
+impl<T: Send + 'static, E: Send + 'static, D: Send + 'static> AsyncJobTask<T, E, D> {
+    // 在 handle 对应的运行时上启动任务；闭包返回的 future 完成时发送 Done
+    pub fn spawn<F, Fut>(handle: &Handle, params: Value, task: F) -> Self
+    where
+        F: FnOnce(Value, UnboundedSender<TaskEvent<T, E>>, UnboundedReceiver<D>) -> Fut,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let (event_tx, event_rx) = mpsc::unbounded_channel();
+        let (data_tx, data_rx) = mpsc::unbounded_channel();
+        let token = CancellationToken::new();
+
+        let mut work = handle.spawn(task(params, event_tx.clone(), data_rx));
+        let cancelled = token.clone();
+        handle.spawn(async move {
+            let event = match cancelled.run_until_cancelled(&mut work).await {
+                None => {
+                    work.abort();
+                    TaskEvent::Cancelled(CancelReason::Requested)
+                }
+                Some(Ok(())) => TaskEvent::Done,
+                // 任务 panic
+                Some(Err(e)) if e.is_panic() => TaskEvent::Panic(panic_message(e.into_panic())),
+                Some(Err(_)) => TaskEvent::Cancelled(CancelReason::Requested),
+            };
+            let _ = event_tx.send(event);
+        });
+
+        AsyncJobTask {
+            event_rx,
+            sender: data_tx,
+            token,
+        }
+    }
+
+    // 等待下一个事件；任务结束且事件取完后返回 None
+    pub async fn recv(&mut self) -> Option<TaskEvent<T, E>> {
+        self.event_rx.recv().await
+    }
+
+    pub fn try_recv(&mut self) -> Option<TaskEvent<T, E>> {
+        self.event_rx.try_recv().ok()
+    }
+
+    pub fn send(&self, data: D) {
+        let _ = self.sender.send(data);
+    }
+
+    // 取消任务，任务 future 在下一个 await 点被丢弃
+    pub fn cancel(&self) {
+        self.token.cancel();
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.token.is_cancelled()
+    }
+
+    // 取消令牌，可以交给其他组件或与子任务共享
+    pub fn cancellation_token(&self) -> CancellationToken {
+        self.token.clone()
+    }
+}
+
+impl<T, E, D> Drop for AsyncJobTask<T, E, D> {
+    fn drop(&mut self) {
+        self.token.cancel(); // 确保任务被清理
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_async_job_task() {
+        let mut job: AsyncJobTask<String, String, i32> =
+            AsyncJobTask::spawn(&Handle::current(), json!({}), |_, sender, mut receiver| async move {
+                while let Some(n) = receiver.recv().await {
+                    if n == 0 {
+                        break;
+                    }
+                    let _ = sender.send(TaskEvent::Data(format!("got {}", n)));
+                }
+            });
+
+        job.send(1);
+        job.send(2);
+        job.send(0);
+        let mut events = Vec::new();
+        while let Some(event) = job.recv().await {
+            let terminal = event.is_terminal();
+            events.push(event);
+            if terminal {
+                break;
+            }
+        }
+        assert!(matches!(
+            events.as_slice(),
+            [TaskEvent::Data(a), TaskEvent::Data(b), TaskEvent::Done] if a == "got 1" && b == "got 2"
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_async_job_task_cancel() {
+        let mut job: AsyncJobTask<(), String, ()> =
+            AsyncJobTask::spawn(&Handle::current(), json!({}), |_, _, _| std::future::pending());
+
+        job.cancel();
+        assert!(job.is_cancelled());
+        assert!(matches!(job.recv().await, Some(TaskEvent::Cancelled(CancelReason::Requested))));
+    }
+}
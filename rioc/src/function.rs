@@ -1,4 +1,12 @@
 use core::fmt;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use may::coroutine;
+use may::sync::mpsc;
+
+use crate::policy::{Policy, PolicyError};
 
 
 pub trait Service<Input,Output> {
@@ -51,6 +59,527 @@ where
     }
 }
 
+/// A service that may update its own state on every call.
+pub trait ServiceMut<Input,Output> {
+    fn call_mut(&mut self, input: Input) -> Output;
+}
+
+pub fn service_mut<F,Input,Output>(f: F) -> FunctionMut<F,Input,Output>
+where F: FnMut(Input) -> Output {
+    FunctionMut::new(f)
+}
+
+pub struct FunctionMut<F,Input,Output>{
+    func: F,
+    _marker: std::marker::PhantomData<(Input,Output)>,
+}
+
+impl<F,Input,Output> FunctionMut<F,Input,Output>
+where F: FnMut(Input) -> Output
+{
+    pub fn new(func: F) -> Self {
+        Self {
+            func,
+            _marker: std::marker::PhantomData,
+        }
+    }
+}
+
+impl<F,Input,Output> fmt::Debug for FunctionMut<F,Input,Output> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FunctionMut")
+            .field("f", &format_args!("{}", std::any::type_name::<F>()))
+            .finish()
+    }
+}
+
+impl<F,Input,Output> ServiceMut<Input,Output> for FunctionMut<F,Input,Output>
+where
+    F: FnMut(Input) -> Output,
+{
+    fn call_mut(&mut self, req: Input) -> Output {
+        (self.func)(req)
+    }
+}
+
+/// Asynchronous counterpart of [`Service`]; `call` returns a future resolving to the output.
+pub trait AsyncService<Input,Output> {
+    type Future: Future<Output = Output>;
+
+    fn call(&self, input: Input) -> Self::Future;
+}
+
+impl<F,Fut,Input,Output> AsyncService<Input,Output> for F
+where
+    F: Fn(Input) -> Fut,
+    Fut: Future<Output = Output>,
+{
+    type Future = Fut;
+
+    fn call(&self, req: Input) -> Fut {
+        self(req)
+    }
+}
+
+pub fn async_service<F,Fut,Input,Output>(f: F) -> AsyncFunction<F,Input,Output>
+where
+    F: Fn(Input) -> Fut,
+    Fut: Future<Output = Output>,
+{
+    AsyncFunction::new(f)
+}
+
+pub struct AsyncFunction<F,Input,Output>{
+    func: F,
+    _marker: std::marker::PhantomData<(Input,Output)>,
+}
+
+impl<F,Fut,Input,Output> AsyncFunction<F,Input,Output>
+where
+    F: Fn(Input) -> Fut,
+    Fut: Future<Output = Output>,
+{
+    pub fn new(func: F) -> Self {
+        Self {
+            func,
+            _marker: std::marker::PhantomData,
+        }
+    }
+}
+
+impl<F,Input,Output> fmt::Debug for AsyncFunction<F,Input,Output> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AsyncFunction")
+            .field("f", &format_args!("{}", std::any::type_name::<F>()))
+            .finish()
+    }
+}
+
+impl<F,Fut,Input,Output> AsyncService<Input,Output> for AsyncFunction<F,Input,Output>
+where
+    F: Fn(Input) -> Fut,
+    Fut: Future<Output = Output>,
+{
+    type Future = Fut;
+
+    fn call(&self, req: Input) -> Fut {
+        (self.func)(req)
+    }
+}
+
+pub trait ServiceExt<Input,Output>: Service<Input,Output> + Sized {
+    /// Lift the service over `Option`: `None` passes through without calling the service.
+    fn optional(self) -> Optional<Self> {
+        Optional { inner: self }
+    }
+
+    /// Turn a panic inside `call` into an `Err` carrying the panic message.
+    fn catch_unwind(self) -> CatchUnwind<Self> {
+        CatchUnwind { inner: self }
+    }
+
+    /// Turn a service over `Vec<I>` into one over single items.
+    /// Calls arriving within `window` of the first one are sent as a single batch of at most `max_batch` items.
+    /// Outputs are matched to inputs by position; see [`BatchError`] for calls that get none.
+    fn batched<I,O>(self, max_batch: usize, window: Duration) -> Batched<I,O>
+    where
+        Self: Service<Vec<I>,Vec<O>> + Send + 'static,
+        I: Send + 'static,
+        O: Send + 'static,
+    {
+        Batched::new(self, max_batch.max(1), window)
+    }
+
+    /// Transform the output of the service with `f`.
+    fn map<F,O2>(self, f: F) -> Map<Self,F,Output>
+    where F: Fn(Output) -> O2
+    {
+        Map { inner: self, f, _marker: std::marker::PhantomData }
+    }
+
+    /// Feed the output of the service into `next`.
+    fn and_then<S2,O2>(self, next: S2) -> AndThen<Self,S2,Output>
+    where S2: Service<Output,O2>
+    {
+        AndThen { first: self, next, _marker: std::marker::PhantomData }
+    }
+
+    /// Retry failed calls and bound each attempt as configured by `policy`.
+    /// The input is cloned for every attempt.
+    fn with_policy(self, policy: Policy) -> WithPolicy<Self> {
+        WithPolicy { inner: Arc::new(self), policy }
+    }
+
+    /// Stop calling the service after `failure_threshold` consecutive errors.
+    /// Calls fail fast with [`CircuitError::Open`] until `cooldown` has passed, then a single trial call decides
+    /// whether the breaker closes again or stays open for another cooldown.
+    fn circuit_breaker(self, failure_threshold: usize, cooldown: Duration) -> CircuitBreaker<Self> {
+        CircuitBreaker {
+            inner: Arc::new(self),
+            failure_threshold: failure_threshold.max(1),
+            cooldown,
+            breaker: Arc::new(Mutex::new(Breaker::Closed(0))),
+        }
+    }
+}
+
+impl<S,Input,Output> ServiceExt<Input,Output> for S
+where S: Service<Input,Output>
+{
+}
+
+#[derive(Debug, Clone)]
+pub struct Optional<S> {
+    inner: S,
+}
+
+impl<S,Input,Output> Service<Option<Input>,Option<Output>> for Optional<S>
+where
+    S: Service<Input,Output>,
+{
+    fn call(&self, req: Option<Input>) -> Option<Output> {
+        req.map(|req| self.inner.call(req))
+    }
+}
+
+/// Service returned by [`ServiceExt::map`].
+pub struct Map<S,F,Output> {
+    inner: S,
+    f: F,
+    _marker: std::marker::PhantomData<Output>,
+}
+
+impl<S: Clone,F: Clone,Output> Clone for Map<S,F,Output> {
+    fn clone(&self) -> Self {
+        Map { inner: self.inner.clone(), f: self.f.clone(), _marker: std::marker::PhantomData }
+    }
+}
+
+impl<S: fmt::Debug,F,Output> fmt::Debug for Map<S,F,Output> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Map")
+            .field("inner", &self.inner)
+            .field("f", &format_args!("{}", std::any::type_name::<F>()))
+            .finish()
+    }
+}
+
+impl<S,F,Input,Output,O2> Service<Input,O2> for Map<S,F,Output>
+where
+    S: Service<Input,Output>,
+    F: Fn(Output) -> O2,
+{
+    fn call(&self, req: Input) -> O2 {
+        (self.f)(self.inner.call(req))
+    }
+}
+
+/// Service returned by [`ServiceExt::and_then`].
+pub struct AndThen<S,S2,Mid> {
+    first: S,
+    next: S2,
+    _marker: std::marker::PhantomData<Mid>,
+}
+
+impl<S: Clone,S2: Clone,Mid> Clone for AndThen<S,S2,Mid> {
+    fn clone(&self) -> Self {
+        AndThen { first: self.first.clone(), next: self.next.clone(), _marker: std::marker::PhantomData }
+    }
+}
+
+impl<S: fmt::Debug,S2: fmt::Debug,Mid> fmt::Debug for AndThen<S,S2,Mid> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AndThen")
+            .field("first", &self.first)
+            .field("next", &self.next)
+            .finish()
+    }
+}
+
+impl<S,S2,Input,Mid,Output> Service<Input,Output> for AndThen<S,S2,Mid>
+where
+    S: Service<Input,Mid>,
+    S2: Service<Mid,Output>,
+{
+    fn call(&self, req: Input) -> Output {
+        self.next.call(self.first.call(req))
+    }
+}
+
+/// A panic captured by [`CatchUnwind`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PanicInfo {
+    pub message: String,
+}
+
+impl fmt::Display for PanicInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "service panicked: {}", self.message)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CatchUnwind<S> {
+    inner: S,
+}
+
+impl<S,Input,Output> Service<Input,Result<Output,PanicInfo>> for CatchUnwind<S>
+where
+    S: Service<Input,Output>,
+{
+    fn call(&self, req: Input) -> Result<Output,PanicInfo> {
+        std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| self.inner.call(req)))
+            .map_err(|payload| PanicInfo { message: panic_message(payload) })
+    }
+}
+
+/// Service returned by [`ServiceExt::with_policy`].
+#[derive(Debug)]
+pub struct WithPolicy<S> {
+    inner: Arc<S>,
+    policy: Policy,
+}
+
+impl<S> Clone for WithPolicy<S> {
+    fn clone(&self) -> Self {
+        WithPolicy { inner: self.inner.clone(), policy: self.policy.clone() }
+    }
+}
+
+impl<S,Input,Output,Error> Service<Input,Result<Output,PolicyError<Error>>> for WithPolicy<S>
+where
+    S: Service<Input,Result<Output,Error>> + Send + Sync + 'static,
+    Input: Clone + Send + Sync + 'static,
+    Output: Send + 'static,
+    Error: Send + 'static,
+{
+    fn call(&self, req: Input) -> Result<Output,PolicyError<Error>> {
+        let inner = self.inner.clone();
+        self.policy.run(move || inner.call(req.clone()))
+    }
+}
+
+/// State of a [`CircuitBreaker`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CircuitState {
+    /// Calls go through; consecutive failures are counted.
+    Closed,
+    /// Calls fail fast until the cooldown has passed.
+    Open,
+    /// The cooldown has passed; the next call is a trial.
+    HalfOpen,
+}
+
+/// The error returned by a [`CircuitBreaker`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CircuitError<E> {
+    /// The breaker is open and the service was not called.
+    Open,
+    /// The service was called and failed.
+    Inner(E),
+}
+
+impl<E: fmt::Display> fmt::Display for CircuitError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CircuitError::Open => write!(f, "circuit breaker is open"),
+            CircuitError::Inner(error) => error.fmt(f),
+        }
+    }
+}
+
+#[derive(Debug)]
+enum Breaker {
+    // number of consecutive failures
+    Closed(usize),
+    // open until the given instant
+    Open(Instant),
+    // a trial call is in flight
+    Trial,
+}
+
+/// Service returned by [`ServiceExt::circuit_breaker`].
+/// Clones share the same breaker.
+#[derive(Debug)]
+pub struct CircuitBreaker<S> {
+    inner: Arc<S>,
+    failure_threshold: usize,
+    cooldown: Duration,
+    breaker: Arc<Mutex<Breaker>>,
+}
+
+impl<S> Clone for CircuitBreaker<S> {
+    fn clone(&self) -> Self {
+        CircuitBreaker {
+            inner: self.inner.clone(),
+            failure_threshold: self.failure_threshold,
+            cooldown: self.cooldown,
+            breaker: self.breaker.clone(),
+        }
+    }
+}
+
+impl<S> CircuitBreaker<S> {
+    pub fn state(&self) -> CircuitState {
+        match *self.breaker.lock().unwrap() {
+            Breaker::Closed(_) => CircuitState::Closed,
+            Breaker::Open(until) if Instant::now() >= until => CircuitState::HalfOpen,
+            Breaker::Open(_) | Breaker::Trial => CircuitState::Open,
+        }
+    }
+
+    // Whether the call may go through; a call after the cooldown becomes the trial.
+    fn acquire(&self) -> bool {
+        let mut breaker = self.breaker.lock().unwrap();
+        match *breaker {
+            Breaker::Closed(_) => true,
+            Breaker::Open(until) if Instant::now() >= until => {
+                *breaker = Breaker::Trial;
+                true
+            }
+            Breaker::Open(_) | Breaker::Trial => false,
+        }
+    }
+
+    fn record(&self, success: bool) {
+        let mut breaker = self.breaker.lock().unwrap();
+        *breaker = match (&*breaker, success) {
+            (_, true) => Breaker::Closed(0),
+            (Breaker::Closed(failures), false) if failures + 1 < self.failure_threshold => {
+                Breaker::Closed(failures + 1)
+            }
+            (_, false) => Breaker::Open(Instant::now() + self.cooldown),
+        };
+    }
+}
+
+impl<S,Input,Output,Error> Service<Input,Result<Output,CircuitError<Error>>> for CircuitBreaker<S>
+where
+    S: Service<Input,Result<Output,Error>>,
+{
+    fn call(&self, req: Input) -> Result<Output,CircuitError<Error>> {
+        if !self.acquire() {
+            return Err(CircuitError::Open);
+        }
+        // A panic counts as a failure, so a panicking trial reopens the breaker instead of leaving it stuck.
+        let result = match std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| self.inner.call(req))) {
+            Ok(result) => result,
+            Err(payload) => {
+                self.record(false);
+                std::panic::resume_unwind(payload);
+            }
+        };
+        self.record(result.is_ok());
+        result.map_err(CircuitError::Inner)
+    }
+}
+
+// Extracts the message passed to `panic!`, if it was a string.
+pub(crate) fn panic_message(payload: Box<dyn std::any::Any + Send>) -> String {
+    match payload.downcast::<String>() {
+        Ok(message) => *message,
+        Err(payload) => match payload.downcast::<&'static str>() {
+            Ok(message) => message.to_string(),
+            Err(_) => "unknown panic".to_string(),
+        },
+    }
+}
+
+/// Why a [`Batched`] call got no output.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BatchError {
+    /// The inner service returned fewer outputs than the batch had inputs; the calls at the end miss out.
+    MissingOutput,
+    /// The inner service panicked while handling the batch.
+    Panicked(String),
+    /// The batching coroutine is gone.
+    Stopped,
+}
+
+impl fmt::Display for BatchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BatchError::MissingOutput => write!(f, "batched service returned fewer outputs than inputs"),
+            BatchError::Panicked(message) => write!(f, "batched service panicked: {}", message),
+            BatchError::Stopped => write!(f, "batching coroutine has stopped"),
+        }
+    }
+}
+
+type BatchItem<I,O> = (I, mpsc::Sender<Result<O,BatchError>>);
+
+/// Service returned by [`ServiceExt::batched`].
+/// The batching coroutine stops once every handle has been dropped.
+pub struct Batched<I,O> {
+    sender: mpsc::Sender<BatchItem<I,O>>,
+}
+
+impl<I,O> Batched<I,O>
+where
+    I: Send + 'static,
+    O: Send + 'static,
+{
+    fn new<S>(inner: S, max_batch: usize, window: Duration) -> Self
+    where S: Service<Vec<I>,Vec<O>> + Send + 'static
+    {
+        let (sender, receiver) = mpsc::channel::<BatchItem<I,O>>();
+        let _ = unsafe { coroutine::spawn(move || {
+            while let Ok(first) = receiver.recv() {
+                let deadline = Instant::now() + window;
+                let mut batch = vec![first];
+                while batch.len() < max_batch {
+                    let remaining = deadline.saturating_duration_since(Instant::now());
+                    if remaining.is_zero() {
+                        break;
+                    }
+                    match receiver.recv_timeout(remaining) {
+                        Ok(item) => batch.push(item),
+                        Err(_) => break,
+                    }
+                }
+                let (inputs, replies): (Vec<I>, Vec<mpsc::Sender<Result<O,BatchError>>>) = batch.into_iter().unzip();
+                // panic 只影响当前批次，协程继续处理后面的调用
+                let outputs = match std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| inner.call(inputs))) {
+                    Ok(outputs) => outputs,
+                    Err(payload) => {
+                        let error = BatchError::Panicked(panic_message(payload));
+                        for reply in replies {
+                            let _ = reply.send(Err(error.clone()));
+                        }
+                        continue;
+                    }
+                };
+                // 输出数量不足时多余的调用方收到 MissingOutput
+                let mut outputs = outputs.into_iter();
+                for reply in replies {
+                    let _ = reply.send(outputs.next().ok_or(BatchError::MissingOutput));
+                }
+            }
+        }) };
+        Batched { sender }
+    }
+}
+
+impl<I,O> Clone for Batched<I,O> {
+    fn clone(&self) -> Self {
+        Batched { sender: self.sender.clone() }
+    }
+}
+
+impl<I,O> fmt::Debug for Batched<I,O> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Batched").finish_non_exhaustive()
+    }
+}
+
+impl<I,O> Service<I,Result<O,BatchError>> for Batched<I,O> {
+    fn call(&self, req: I) -> Result<O,BatchError> {
+        let (reply, response) = mpsc::channel();
+        self.sender.send((req, reply)).map_err(|_| BatchError::Stopped)?;
+        response.recv().unwrap_or(Err(BatchError::Stopped))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::sync::Arc;
@@ -89,6 +618,180 @@ mod tests {
         );
     }
 
+    #[test]
+    fn service_optional() {
+        let svc = service(|x: i32| x * 2).optional();
+        assert_eq!(svc.call(None), None);
+        assert_eq!(svc.call(Some(21)), Some(42));
+    }
+
+    #[test]
+    fn service_and_then_map() {
+        let svc = service(|x: i32| x + 1).and_then(service(|x: i32| x * 2));
+        assert_eq!(svc.call(3), 8);
+
+        let svc = svc.map(|x: i32| format!("={}", x));
+        assert_eq!(svc.call(0), "=2");
+        assert!(format!("{:?}", svc).starts_with("Map { inner: AndThen { first: Function { f: "));
+    }
+
+    #[test]
+    fn service_catch_unwind() {
+        let svc = service(|x: i32| {
+            if x < 0 {
+                panic!("negative input: {}", x);
+            }
+            x * 2
+        }).catch_unwind();
+        assert_eq!(svc.call(21), Ok(42));
+        assert_eq!(svc.call(-1), Err(PanicInfo { message: "negative input: -1".to_string() }));
+
+        let svc = service(|_: ()| -> i32 { panic!("boom") }).catch_unwind();
+        assert_eq!(svc.call(()).unwrap_err().message, "boom");
+    }
+
+    #[test]
+    fn service_batched() {
+        let batches = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let seen = batches.clone();
+        let svc = service(move |xs: Vec<i32>| {
+            seen.lock().unwrap().push(xs.len());
+            xs.into_iter().map(|x| x * 10).collect::<Vec<_>>()
+        }).batched(8, Duration::from_millis(200));
+
+        let call_all = |svc: &Batched<i32, i32>| {
+            let handles = (1..=3).map(|x| {
+                let svc = svc.clone();
+                std::thread::spawn(move || svc.call(x))
+            }).collect::<Vec<_>>();
+            handles.into_iter().map(|h| h.join().unwrap()).collect::<Vec<_>>()
+        };
+        assert_eq!(call_all(&svc), vec![Ok(10), Ok(20), Ok(30)]);
+        assert_eq!(*batches.lock().unwrap(), vec![3]);
+
+        // 超过 max_batch 时拆分成多个批次
+        batches.lock().unwrap().clear();
+        let seen = batches.clone();
+        let svc = service(move |xs: Vec<i32>| {
+            seen.lock().unwrap().push(xs.len());
+            xs
+        }).batched(2, Duration::from_millis(200));
+        assert_eq!(call_all(&svc), vec![Ok(1), Ok(2), Ok(3)]);
+        assert_eq!(*batches.lock().unwrap(), vec![2, 1]);
+    }
+
+    #[test]
+    fn service_batched_short_output_and_panic() {
+        let svc = service(|xs: Vec<i32>| {
+            if xs.contains(&0) {
+                panic!("zero in batch");
+            }
+            // 丢掉最后一个输出
+            xs.iter().take(xs.len() - 1).map(|x| x * 10).collect::<Vec<_>>()
+        }).batched(2, Duration::from_millis(200));
+
+        let call_pair = |a: i32, b: i32| {
+            let (first, second) = (svc.clone(), svc.clone());
+            let first = std::thread::spawn(move || first.call(a));
+            std::thread::sleep(Duration::from_millis(20));
+            let second = std::thread::spawn(move || second.call(b));
+            (first.join().unwrap(), second.join().unwrap())
+        };
+        assert_eq!(call_pair(1, 2), (Ok(10), Err(BatchError::MissingOutput)));
+
+        // panic 之后批处理协程仍在运行
+        assert_eq!(
+            call_pair(0, 3),
+            (Err(BatchError::Panicked("zero in batch".to_string())), Err(BatchError::Panicked("zero in batch".to_string())))
+        );
+        assert_eq!(call_pair(4, 5), (Ok(40), Err(BatchError::MissingOutput)));
+    }
+
+    #[test]
+    fn service_mut_keeps_state() {
+        let mut total = 0;
+        {
+            let mut svc = service_mut(|x: i32| {
+                total += x;
+                total
+            });
+            assert_eq!(svc.call_mut(1), 1);
+            assert_eq!(svc.call_mut(2), 3);
+            assert_eq!(svc.call_mut(3), 6);
+        }
+        assert_eq!(total, 6);
+    }
+
+    #[test]
+    fn service_circuit_breaker() {
+        let healthy = Arc::new(std::sync::atomic::AtomicBool::new(false));
+        let flag = healthy.clone();
+        let svc = service(move |x: i32| match flag.load(std::sync::atomic::Ordering::SeqCst) {
+            true => Ok(x),
+            false => Err("down"),
+        })
+        .circuit_breaker(2, Duration::from_millis(50));
+
+        assert_eq!(svc.call(1), Err(CircuitError::Inner("down")));
+        assert_eq!(svc.state(), CircuitState::Closed);
+        assert_eq!(svc.call(2), Err(CircuitError::Inner("down")));
+        assert_eq!(svc.state(), CircuitState::Open);
+
+        // fails fast during the cooldown even though the service has recovered
+        healthy.store(true, std::sync::atomic::Ordering::SeqCst);
+        assert_eq!(svc.call(3), Err(CircuitError::Open));
+
+        std::thread::sleep(Duration::from_millis(60));
+        assert_eq!(svc.state(), CircuitState::HalfOpen);
+        assert_eq!(svc.call(4), Ok(4));
+        assert_eq!(svc.state(), CircuitState::Closed);
+        assert_eq!(svc.call(5), Ok(5));
+    }
+
+    #[test]
+    fn service_circuit_breaker_reopens_after_panicking_trial() {
+        let panicking = Arc::new(std::sync::atomic::AtomicBool::new(true));
+        let flag = panicking.clone();
+        let svc = service(move |x: i32| -> Result<i32, &str> {
+            if flag.load(std::sync::atomic::Ordering::SeqCst) {
+                panic!("trial exploded");
+            }
+            Ok(x)
+        })
+        .circuit_breaker(1, Duration::from_millis(50));
+
+        let trip = svc.clone();
+        assert!(std::panic::catch_unwind(move || trip.call(1)).is_err());
+        assert_eq!(svc.state(), CircuitState::Open);
+
+        std::thread::sleep(Duration::from_millis(60));
+        assert_eq!(svc.state(), CircuitState::HalfOpen);
+        let trial = svc.clone();
+        assert!(std::panic::catch_unwind(move || trial.call(2)).is_err());
+        // the panicking trial reopened the breaker for another cooldown
+        assert_eq!(svc.state(), CircuitState::Open);
+        assert_eq!(svc.call(3), Err(CircuitError::Open));
+
+        panicking.store(false, std::sync::atomic::Ordering::SeqCst);
+        std::thread::sleep(Duration::from_millis(60));
+        assert_eq!(svc.call(4), Ok(4));
+        assert_eq!(svc.state(), CircuitState::Closed);
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn async_service_awaits_closure() {
+        let svc = async_service(|x: i32| async move {
+            tokio::task::yield_now().await;
+            x + 1
+        });
+        assert_eq!(AsyncService::call(&svc, 41).await, 42);
+
+        // 返回 future 的闭包本身也是 AsyncService
+        let double = |x: i32| async move { x * 2 };
+        assert_eq!(AsyncService::call(&double, 21).await, 42);
+    }
+
     #[test]
     fn service_fn_exa() {
         let f = Function::new (|_x: i32| "hello");
@@ -1,27 +1,279 @@
 use std::sync::Weak;
-use std::{cell::RefCell, collections::HashMap};
+use std::collections::HashMap;
 use std::{any, clone};
+use std::any::{Any, TypeId};
 use std::collections::VecDeque;
 use std::error::Error;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex, RwLock};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::hash::{BuildHasher, Hasher};
+use std::collections::hash_map::RandomState;
+use std::fmt;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
-use crate::function::{service, Function, Service};
+use crate::function::{panic_message, service, Function, Service};
 
-#[derive(Debug, Clone)]
+pub const TRACE_ID_KEY: &str = "trace_id";
+pub const SEQUENCE_KEY: &str = "seq";
+
+// 链级别的共享状态，按类型存放，跨请求保留
+#[derive(Clone, Default)]
+pub struct ChainState {
+    values: Arc<RwLock<HashMap<TypeId, Arc<dyn Any + Send + Sync>>>>,
+}
+
+impl ChainState {
+    pub fn get<T: Any + Send + Sync>(&self) -> Option<Arc<T>> {
+        let value = self.values.read().unwrap().get(&TypeId::of::<T>())?.clone();
+        value.downcast::<T>().ok()
+    }
+
+    fn set<T: Any + Send + Sync>(&self, value: T) {
+        self.values.write().unwrap().insert(TypeId::of::<T>(), Arc::new(value));
+    }
+
+    fn is_empty(&self) -> bool {
+        self.values.read().unwrap().is_empty()
+    }
+}
+
+impl fmt::Debug for ChainState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ChainState")
+            .field("len", &self.values.read().unwrap().len())
+            .finish()
+    }
+}
+
+#[derive(Debug, Clone, Default)]
 pub struct ChainContext {
     pub data: HashMap<String,String>,
+    // 整个请求的截止时间，在链入口设置，每一层处理前都会检查
+    pub deadline: Option<Instant>,
+    // 由链入口写入，指向 LayerChain 的共享状态
+    pub state: Option<ChainState>,
+    // 按类型存放的请求级数据，通过 insert/get 访问
+    typed: TypedValues,
+    // 分块传输时表示后面还有分块
+    more: bool,
 }
 
-#[derive(Debug, Clone)]
+// 克隆上下文时共享同一份值，不会深拷贝
+#[derive(Clone, Default)]
+struct TypedValues(HashMap<TypeId, Arc<dyn Any + Send + Sync>>);
+
+impl fmt::Debug for TypedValues {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TypedValues").field("len", &self.0.len()).finish()
+    }
+}
+
+impl ChainContext {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.deadline = Some(Instant::now() + timeout);
+    }
+
+    // 剩余的时间预算；没有设置截止时间时返回 None
+    pub fn remaining(&self) -> Option<Duration> {
+        self.deadline.map(|deadline| deadline.saturating_duration_since(Instant::now()))
+    }
+
+    pub fn is_expired(&self) -> bool {
+        matches!(self.deadline, Some(deadline) if Instant::now() >= deadline)
+    }
+
+    // 当前请求的追踪 id，由 LayerChain::handle_inbound_traced 在入口处写入
+    pub fn trace_id(&self) -> Option<&str> {
+        self.data.get(TRACE_ID_KEY).map(String::as_str)
+    }
+
+    pub fn set_trace_id(&mut self, trace_id: impl Into<String>) {
+        self.data.insert(TRACE_ID_KEY.to_string(), trace_id.into());
+    }
+
+    // 读取通过 LayerChain::set_state 设置的共享状态
+    pub fn state<T: Any + Send + Sync>(&self) -> Option<Arc<T>> {
+        self.state.as_ref()?.get::<T>()
+    }
+
+    // 按类型保存一个值，同类型的旧值被替换；和字符串 data 互不影响
+    pub fn insert<T: Any + Send + Sync>(&mut self, value: T) {
+        self.typed.0.insert(TypeId::of::<T>(), Arc::new(value));
+    }
+
+    pub fn get<T: Any + Send + Sync>(&self) -> Option<&T> {
+        self.typed.0.get(&TypeId::of::<T>())?.downcast_ref::<T>()
+    }
+}
+
+// 生成形如 UUID 的随机 id（8-4-4-4-12 个十六进制字符）
+fn new_trace_id() -> String {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let count = COUNTER.fetch_add(1, Ordering::Relaxed);
+    let mut halves = [0u64; 2];
+    for (i, half) in halves.iter_mut().enumerate() {
+        let mut hasher = RandomState::new().build_hasher();
+        hasher.write_u64(count);
+        hasher.write_usize(i);
+        hasher.write_u128(SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_nanos()));
+        *half = hasher.finish();
+    }
+    let hex = format!("{:016x}{:016x}", halves[0], halves[1]);
+    format!("{}-{}-{}-{}-{}", &hex[0..8], &hex[8..12], &hex[12..16], &hex[16..20], &hex[20..32])
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LayerError {
+    DeadlineExceeded,
+    OutOfOrder { expected: u64, got: u64 },
+    // layer_index 从 head 开始计数
+    HandlerPanicked { layer_index: usize, message: String },
+    // 处理函数返回的错误，保留原始信息
+    HandlerFailed(String),
+    // 上层已被释放，Weak 升级失败
+    UpstreamDropped,
+    // 链中没有任何层
+    EmptyChain,
+    // 严格模式下端点层不处理该方向的数据
+    DirectionRejected { inbound: bool },
+    // 恢复时暂停的层已被释放
+    PausedLayerDropped,
+    // 链没有在给定的时间内处理完
+    Timeout,
+}
+
+impl fmt::Display for LayerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LayerError::DeadlineExceeded => write!(f, "deadline exceeded"),
+            LayerError::OutOfOrder { expected, got } => {
+                write!(f, "out of order: expected sequence {}, got {}", expected, got)
+            }
+            LayerError::HandlerPanicked { layer_index, message } => {
+                write!(f, "handler of layer {} panicked: {}", layer_index, message)
+            }
+            LayerError::HandlerFailed(message) => write!(f, "handler failed: {}", message),
+            LayerError::UpstreamDropped => write!(f, "upstream layer dropped"),
+            LayerError::EmptyChain => write!(f, "no layers in the chain"),
+            LayerError::DirectionRejected { inbound: true } => {
+                write!(f, "inbound payload rejected by outbound-only layer")
+            }
+            LayerError::DirectionRejected { inbound: false } => {
+                write!(f, "outbound payload rejected by inbound-only layer")
+            }
+            LayerError::PausedLayerDropped => write!(f, "paused layer no longer exists"),
+            LayerError::Timeout => write!(f, "chain processing timed out"),
+        }
+    }
+}
+
+impl Error for LayerError {}
+
+// 处理函数可以直接返回字符串错误
+impl From<String> for LayerError {
+    fn from(message: String) -> Self {
+        LayerError::HandlerFailed(message)
+    }
+}
+
+impl From<&str> for LayerError {
+    fn from(message: &str) -> Self {
+        LayerError::HandlerFailed(message.to_string())
+    }
+}
+
+fn check_deadline(req: &Option<PayLoad>) -> Result<(), LayerError> {
+    match req {
+        Some(payload) if payload.ctx.is_expired() => Err(LayerError::DeadlineExceeded),
+        _ => Ok(()),
+    }
+}
+
+#[derive(Debug, Clone, Default)]
 pub struct PayLoad {
     pub data: Option<String>,
-    pub ctx: Option<ChainContext>,
+    // 二进制内容（帧、校验和、TLS 记录等），链原样传递，不经过 UTF-8 转换
+    pub bytes: Option<Vec<u8>>,
+    pub ctx: ChainContext,
+}
+
+impl PayLoad {
+    // 空负载：没有数据，但总是带有一个上下文
+    pub fn empty() -> Self {
+        Self::default()
+    }
+
+    pub fn of(data: String) -> Self {
+        Self::text(data)
+    }
+
+    pub fn text(data: impl Into<String>) -> Self {
+        Self {
+            data: Some(data.into()),
+            bytes: None,
+            ctx: ChainContext::new(),
+        }
+    }
+
+    pub fn bytes(bytes: Vec<u8>) -> Self {
+        Self {
+            data: None,
+            bytes: Some(bytes),
+            ctx: ChainContext::new(),
+        }
+    }
+
+    pub fn with_ctx(data: String, ctx: ChainContext) -> Self {
+        Self {
+            data: Some(data),
+            bytes: None,
+            ctx,
+        }
+    }
+
+    pub fn as_text(&self) -> Option<&str> {
+        self.data.as_deref()
+    }
+
+    // 优先返回二进制内容，没有时返回文本的字节
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        self.bytes.as_deref().or_else(|| self.data.as_deref().map(str::as_bytes))
+    }
+
+    // 流中的一个分块；more 为 true 表示后面还有分块
+    pub fn chunk(data: impl Into<String>, more: bool) -> Self {
+        let mut payload = Self::text(data);
+        payload.set_more(more);
+        payload
+    }
+
+    pub fn has_more(&self) -> bool {
+        self.ctx.more
+    }
+
+    // 处理函数通过这个标记告诉下一层后面还有分块
+    pub fn set_more(&mut self, more: bool) {
+        self.ctx.more = more;
+    }
 }
 
 #[derive(Clone, Debug)]
 pub enum Direction{
    Inbound,
    Outbound,
+   // 处理函数暂停了链，调用者之后用 LayerChain::resume 继续
+   Pending(ResumeToken),
+}
+
+// 暂停点：记录暂停的层以及它处理的方向，由链在返回途中填写
+#[derive(Clone, Debug, Default)]
+pub struct ResumeToken {
+    layer: Option<WeakLayer>,
+    inbound: bool,
 }
 
 #[derive(Debug, Clone)]
@@ -30,30 +282,123 @@ pub struct LayerResult {
     pub data: Option<PayLoad>,
 }
 
+impl LayerResult {
+    // 暂停链的处理结果，data 会原样返回给调用者
+    pub fn pending(data: Option<PayLoad>) -> Self {
+        Self {
+            direction: Direction::Pending(ResumeToken::default()),
+            data,
+        }
+    }
+
+    pub fn is_pending(&self) -> bool {
+        matches!(self.direction, Direction::Pending(_))
+    }
+
+    pub fn resume_token(&self) -> Option<ResumeToken> {
+        match &self.direction {
+            Direction::Pending(token) => Some(token.clone()),
+            _ => None,
+        }
+    }
+}
+
+// 只有离暂停点最近的调用者知道暂停的是哪一层
+fn mark_paused_layer(result: &mut LayerResult, layer: &SharedLayer) {
+    if let Direction::Pending(token) = &mut result.direction {
+        if token.layer.is_none() {
+            token.layer = Some(Arc::downgrade(layer));
+        }
+    }
+}
+
+// 在独立线程上运行 f 并等待到 timeout；线程中的 panic 会传递给调用方
+// 工作线程只拿到层的处理函数和连接，不持有任何锁，超时后卡住的处理函数不会妨碍修改链
+fn run_with_timeout<F>(layer: &SharedLayer, timeout: Duration, f: F) -> Result<LayerResult, LayerError>
+where
+    F: FnOnce(&Hop) -> Result<LayerResult, LayerError> + Send + 'static,
+{
+    let hop = Hop::of(layer);
+    let (tx, rx) = std::sync::mpsc::channel();
+    let worker = std::thread::spawn(move || {
+        let _ = tx.send(f(&hop));
+    });
+    match rx.recv_timeout(timeout) {
+        Ok(result) => result,
+        Err(std::sync::mpsc::RecvTimeoutError::Timeout) => Err(LayerError::Timeout),
+        Err(std::sync::mpsc::RecvTimeoutError::Disconnected) => match worker.join() {
+            Err(payload) => std::panic::resume_unwind(payload),
+            Ok(()) => Err(LayerError::Timeout),
+        },
+    }
+}
+
+// 给每个分块设置 more 标记：只有最后一块为 false
+fn mark_chunks<I: Iterator<Item = PayLoad>>(chunks: I) -> impl Iterator<Item = PayLoad> {
+    let mut chunks = chunks.peekable();
+    std::iter::from_fn(move || {
+        let mut chunk = chunks.next()?;
+        chunk.set_more(chunks.peek().is_some());
+        Some(chunk)
+    })
+}
+
+fn mark_paused_direction(result: &mut LayerResult, inbound: bool) {
+    if let Direction::Pending(token) = &mut result.direction {
+        token.inbound = inbound;
+    }
+}
+
 
 pub struct ProtocolAware{
-    func: Box<dyn Fn(Option<PayLoad>) -> Result<LayerResult, String>>,
+    func: Box<dyn Fn(Option<PayLoad>) -> Result<LayerResult, LayerError> + Send + Sync>,
 }
 
-impl Service<Option<PayLoad>,Result<LayerResult, String>> for ProtocolAware {
-    fn call(&self, input: Option<PayLoad>) -> Result<LayerResult, String> {
+impl Service<Option<PayLoad>,Result<LayerResult, LayerError>> for ProtocolAware {
+    fn call(&self, input: Option<PayLoad>) -> Result<LayerResult, LayerError> {
         (self.func)(input)
     }
 }
 
-pub fn protocol_handler(f: impl Fn(Option<PayLoad>) -> Result<LayerResult, String> + 'static) -> ProtocolAware {
+pub fn protocol_handler(f: impl Fn(Option<PayLoad>) -> Result<LayerResult, LayerError> + Send + Sync + 'static) -> ProtocolAware {
    ProtocolAware { func: Box::new(f)}
 }
 
-pub type SharedLayer = Arc<RefCell<Layer>>;
-pub type WeakLayer = Weak<RefCell<Layer>>;
+// 层之间通过读写锁共享，链因此是 Send + Sync，可以交给线程池处理
+pub type SharedLayer = Arc<RwLock<Layer>>;
+pub type WeakLayer = Weak<RwLock<Layer>>;
+
+// 层实际处理的方向；单向层在另一个方向上直接透传
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LayerKind {
+    Duplex,
+    InboundOnly,
+    OutboundOnly,
+    Passthrough,
+}
+
+// 供检查工具使用的层信息，在 LayerBuilder 构建时确定
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LayerInspection {
+    pub name: Option<String>,
+    pub inbound: bool,  // 是否有真实的入站处理函数（而不是透传）
+    pub outbound: bool, // 是否有真实的出站处理函数（而不是透传）
+    pub codec: Option<String>,
+}
 
 #[derive(Clone)]
 pub struct Layer {
     pub handle_inbound: Arc<Box<ProtocolAware>>,
     pub handle_outbound: Arc<Box<ProtocolAware>>,
+    // 可选的 flush 钩子，以 None 调用，返回缓冲中尚未发出的出站数据
+    pub handle_flush: Option<Arc<ProtocolAware>>,
     pub lo_layer: Option<SharedLayer>,
     pub up_layer: Option<WeakLayer>,
+    pub kind: LayerKind,
+    pub name: Option<String>,
+    pub codec: Option<String>,
+    // 开启 panic 隔离时记录该层在链中的位置
+    panic_isolation: Option<usize>,
 }
 
 impl Layer {
@@ -64,52 +409,128 @@ impl Layer {
         Self {
             handle_inbound,
             handle_outbound,
+            handle_flush: None,
             lo_layer: None,
             up_layer: None,
+            kind: LayerKind::Duplex,
+            name: None,
+            codec: None,
+            panic_isolation: None,
         }
     }
 
-    pub fn handle_inbound(&self, req: Option<PayLoad>) -> Result<LayerResult, String> {
-        // 先执行 call，拿到结果，避免嵌套 borrow
-        let result = self.handle_inbound.call(req);
-        if result.is_err() {
-            return Err("failed to handle inbound request".into());
-        }
-        let result = result.unwrap();
-        let mut cloned_result = result.clone();
-
-        let (direction, data) = (result.direction, result.data);
+    // 只处理入站的层，出站数据原样向下传递
+    pub fn inbound_only(
+        handle: impl Fn(Option<PayLoad>) -> Result<LayerResult, LayerError> + Send + Sync + 'static,
+    ) -> SharedLayer {
+        LayerBuilder::new()
+            .with_inbound_fn(handle)
+            .with_outbound_passthrough()
+            .build()
+            .unwrap()
+    }
 
-        let upstream = self.up_layer.clone();
-        let downstream = self.lo_layer.clone();
+    // 只处理出站的层，入站数据原样向上传递
+    pub fn outbound_only(
+        handle: impl Fn(Option<PayLoad>) -> Result<LayerResult, LayerError> + Send + Sync + 'static,
+    ) -> SharedLayer {
+        LayerBuilder::new()
+            .with_inbound_passthrough()
+            .with_outbound_fn(handle)
+            .build()
+            .unwrap()
+    }
 
-        match direction {
-            Direction::Inbound => {
-                if let Some(upstream) = upstream {
-                    if let Some(upstream) = upstream.upgrade(){
-                        cloned_result = upstream.borrow().handle_inbound(data)?;
-                    }else{
-                        return Err("failed to handle inbound request".into());
-                    }
-                }
-            }
-            Direction::Outbound => {
-                if let Some(downstream) = downstream {
-                    cloned_result = downstream.borrow().handle_outbound(data)?;
-                }
-            }
+    pub fn describe(&self) -> String {
+        match self.kind {
+            LayerKind::Duplex => "duplex".to_string(),
+            LayerKind::InboundOnly => "inbound-only (outbound passthrough)".to_string(),
+            LayerKind::OutboundOnly => "outbound-only (inbound passthrough)".to_string(),
+            LayerKind::Passthrough => "passthrough".to_string(),
         }
+    }
 
-        Ok(cloned_result)
+    pub fn inspect(&self) -> LayerInspection {
+        LayerInspection {
+            name: self.name.clone(),
+            inbound: matches!(self.kind, LayerKind::Duplex | LayerKind::InboundOnly),
+            outbound: matches!(self.kind, LayerKind::Duplex | LayerKind::OutboundOnly),
+            codec: self.codec.clone(),
+        }
     }
 
-    pub fn handle_outbound(&self, req: Option<PayLoad>) ->  Result<LayerResult, String> {
-        // 先执行 call，拿到结果，避免嵌套 borrow
-        let result: Result<LayerResult, String> = self.handle_outbound.call(req);
-        if result.is_err() {
-            return Err("failed to handle outbound request".into());
+    // 取出处理请求需要的处理函数和上下连接
+    fn hop(&self) -> Hop {
+        Hop {
+            handle_inbound: self.handle_inbound.clone(),
+            handle_outbound: self.handle_outbound.clone(),
+            handle_flush: self.handle_flush.clone(),
+            lo_layer: self.lo_layer.clone(),
+            up_layer: self.up_layer.clone(),
+            panic_isolation: self.panic_isolation,
         }
-        let result = result.unwrap();
+    }
+
+    pub fn handle_inbound(&self, req: Option<PayLoad>) -> Result<LayerResult, LayerError> {
+        self.hop().handle(req, true)
+    }
+
+    pub fn handle_outbound(&self, req: Option<PayLoad>) ->  Result<LayerResult, LayerError> {
+        self.hop().handle(req, false)
+    }
+
+    // 调用 flush 钩子，把吐出的数据按出站方向继续向下传递；没有钩子时返回 None
+    pub fn flush(&self) -> Option<Result<LayerResult, LayerError>> {
+        self.hop().flush()
+    }
+}
+
+// 从层中取出的处理函数和上下连接。取出后立即释放该层的读锁，再调用处理函数或进入相邻的层，
+// 这样把请求折返的层不会在同一线程上重复加读锁，排队的写锁也不会和正在处理的请求互相等待
+#[derive(Clone)]
+#[allow(clippy::redundant_allocation)] // 和 Layer 的字段类型保持一致
+struct Hop {
+    handle_inbound: Arc<Box<ProtocolAware>>,
+    handle_outbound: Arc<Box<ProtocolAware>>,
+    handle_flush: Option<Arc<ProtocolAware>>,
+    lo_layer: Option<SharedLayer>,
+    up_layer: Option<WeakLayer>,
+    panic_isolation: Option<usize>,
+}
+
+impl Hop {
+    fn of(layer: &SharedLayer) -> Self {
+        layer.read().unwrap().hop()
+    }
+
+    // 调用处理函数；开启 panic 隔离时把 panic 转换为 HandlerPanicked 错误
+    fn invoke(&self, handler: &ProtocolAware, req: Option<PayLoad>) -> Result<LayerResult, LayerError> {
+        let Some(layer_index) = self.panic_isolation else {
+            return handler.call(req);
+        };
+        std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| handler.call(req))).unwrap_or_else(|payload| {
+            Err(LayerError::HandlerPanicked { layer_index, message: panic_message(payload) })
+        })
+    }
+
+    fn handle(&self, req: Option<PayLoad>, inbound: bool) -> Result<LayerResult, LayerError> {
+        check_deadline(&req)?;
+        let handler = if inbound { &self.handle_inbound } else { &self.handle_outbound };
+        let result = self.invoke(handler, req)?;
+        self.route(result, inbound)
+    }
+
+    fn flush(&self) -> Option<Result<LayerResult, LayerError>> {
+        let hook = self.handle_flush.clone()?;
+        Some(self.invoke(&hook, None).and_then(|result| match result.data {
+            // 缓冲为空时不再惊动下面的层
+            None => Ok(result),
+            Some(_) => self.route(result, false),
+        }))
+    }
+
+    // 按处理结果的方向把数据交给上层或下层；inbound 表示结果来自入站处理
+    fn route(&self, result: LayerResult, inbound: bool) -> Result<LayerResult, LayerError> {
         let mut cloned_result = result.clone();
 
         let (direction, data) = (result.direction, result.data);
@@ -121,17 +542,20 @@ impl Layer {
             Direction::Inbound => {
                 if let Some(upstream) = upstream {
                     if let Some(upstream) = upstream.upgrade(){
-                        cloned_result = upstream.borrow().handle_inbound(data)?;
-                    }else {
-                        return Err("failed to handle inbound request".into());
-                    }               
+                        cloned_result = Hop::of(&upstream).handle(data, true)?;
+                        mark_paused_layer(&mut cloned_result, &upstream);
+                    }else{
+                        return Err(LayerError::UpstreamDropped);
+                    }
                 }
             }
             Direction::Outbound => {
                 if let Some(downstream) = downstream {
-                    cloned_result = downstream.borrow().handle_outbound(data)?;
+                    cloned_result = Hop::of(&downstream).handle(data, false)?;
+                    mark_paused_layer(&mut cloned_result, &downstream);
                 }
             }
+            Direction::Pending(_) => mark_paused_direction(&mut cloned_result, inbound),
         }
 
         Ok(cloned_result)
@@ -141,6 +565,11 @@ impl Layer {
 pub struct LayerBuilder {
     hanlde_inbound: Option<Arc<Box<ProtocolAware>>>,
     handle_outbound: Option<Arc<Box<ProtocolAware>>>,
+    handle_flush: Option<Arc<ProtocolAware>>,
+    inbound_passthrough: bool,
+    outbound_passthrough: bool,
+    name: Option<String>,
+    codec: Option<String>,
 }
 
 impl LayerBuilder {
@@ -148,42 +577,110 @@ impl LayerBuilder {
         Self {
             hanlde_inbound: None,
             handle_outbound: None,
+            handle_flush: None,
+            inbound_passthrough: false,
+            outbound_passthrough: false,
+            name: None,
+            codec: None,
         }
     }
 
+    pub fn with_name(mut self, name: impl Into<String>) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    // 记录该层使用的编解码器名称，仅用于检查
+    pub fn with_codec(mut self, codec: impl Into<String>) -> Self {
+        self.codec = Some(codec.into());
+        self
+    }
+
+    // 入站数据原样向上传递
+    pub fn with_inbound_passthrough(mut self) -> Self {
+        self = self.with_inbound_fn(|req| Ok(LayerResult { direction: Direction::Inbound, data: req }));
+        self.inbound_passthrough = true;
+        self
+    }
+
+    // 出站数据原样向下传递
+    pub fn with_outbound_passthrough(mut self) -> Self {
+        self = self.with_outbound_fn(|req| Ok(LayerResult { direction: Direction::Outbound, data: req }));
+        self.outbound_passthrough = true;
+        self
+    }
+
     pub fn with_inbound_fn(
         mut self,
-        handle: impl Fn(Option<PayLoad>) -> Result<LayerResult,String> + 'static,
+        handle: impl Fn(Option<PayLoad>) -> Result<LayerResult, LayerError> + Send + Sync + 'static,
     ) -> Self {
         let handle = ProtocolAware { func: Box::new(handle) };
         self.hanlde_inbound = Some(Arc::new(Box::new(handle)));
+        self.inbound_passthrough = false;
         self
     }
 
     pub fn with_outbound_fn(
         mut self,
-        handle: impl Fn(Option<PayLoad>) -> Result<LayerResult,String> + 'static,
+        handle: impl Fn(Option<PayLoad>) -> Result<LayerResult, LayerError> + Send + Sync + 'static,
     ) -> Self {
         let handle = ProtocolAware { func: Box::new(handle) };
         self.handle_outbound = Some(Arc::new(Box::new(handle)));
+        self.outbound_passthrough = false;
+        self
+    }
+
+    // 关闭链之前由 LayerChain::flush 调用，用于吐出缓冲的数据
+    pub fn with_flush_fn(
+        mut self,
+        handle: impl Fn(Option<PayLoad>) -> Result<LayerResult, LayerError> + Send + Sync + 'static,
+    ) -> Self {
+        let handle = ProtocolAware { func: Box::new(handle) };
+        self.handle_flush = Some(Arc::new(handle));
         self
     }
 
-    pub fn build(self) -> Result<Arc<RefCell<Layer>>, String> {
+    pub fn build(self) -> Result<SharedLayer, String> {
         let inbound = self.hanlde_inbound.ok_or("inbound handler not set")?;
         let outbound = self.handle_outbound.ok_or("outbound handler not set")?;
-        Ok(Arc::new(RefCell::new(Layer {
-            handle_inbound: inbound,
-            handle_outbound: outbound,
-            up_layer: None,
-            lo_layer: None,
-        })))
+        let mut layer = Layer::new(inbound, outbound);
+        layer.kind = match (self.inbound_passthrough, self.outbound_passthrough) {
+            (false, false) => LayerKind::Duplex,
+            (false, true) => LayerKind::InboundOnly,
+            (true, false) => LayerKind::OutboundOnly,
+            (true, true) => LayerKind::Passthrough,
+        };
+        layer.name = self.name;
+        layer.codec = self.codec;
+        layer.handle_flush = self.handle_flush;
+        Ok(Arc::new(RwLock::new(layer)))
     }
 }
 
 pub struct LayerChain {
     head: Option<SharedLayer>,
     tail: Option<SharedLayer>,
+    strict_directions: bool,
+    // 有序模式下入站的下一个序号和出站期望的下一个序号
+    ordered: Option<(AtomicU64, AtomicU64)>,
+    state: ChainState,
+    panic_isolation: bool,
+    context_pool: Option<ContextPool>,
+}
+
+// 复用已清空的 ChainContext，避免每个请求都重新分配 HashMap
+struct ContextPool {
+    idle: Mutex<Vec<ChainContext>>,
+    max_idle: usize,
+    created: AtomicUsize,
+    reused: AtomicUsize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ContextPoolStats {
+    pub created: usize, // 新分配的上下文数量
+    pub reused: usize,  // 从池中取出复用的次数
+    pub idle: usize,    // 当前池中空闲的上下文数量
 }
 
 impl LayerChain {
@@ -191,60 +688,529 @@ impl LayerChain {
         Self {
             head: None,
             tail: None,
+            strict_directions: false,
+            ordered: None,
+            state: ChainState::default(),
+            panic_isolation: false,
+            context_pool: None,
+        }
+    }
+
+    // 上下文池：每个请求在入口从池中拿到一个上下文，请求原有的上下文内容被移入其中。
+    // 结果负载原样带回上下文（追踪 id、有序模式的序号等都保留）；调用者用完结果后
+    // 通过 recycle 交还负载，链清空其上下文后放回池中，池中最多保留 max_idle 个
+    pub fn with_context_pool(mut self, max_idle: usize) -> Self {
+        self.context_pool = Some(ContextPool {
+            idle: Mutex::new(Vec::with_capacity(max_idle)),
+            max_idle,
+            created: AtomicUsize::new(0),
+            reused: AtomicUsize::new(0),
+        });
+        self
+    }
+
+    pub fn context_pool_stats(&self) -> Option<ContextPoolStats> {
+        self.context_pool.as_ref().map(|pool| ContextPoolStats {
+            created: pool.created.load(Ordering::Relaxed),
+            reused: pool.reused.load(Ordering::Relaxed),
+            idle: pool.idle.lock().unwrap().len(),
+        })
+    }
+
+    // 入口处为请求换上池中的上下文；没有开启上下文池时原样返回
+    fn pooled(&self, req: Option<PayLoad>) -> Option<PayLoad> {
+        let Some(pool) = &self.context_pool else {
+            return req;
+        };
+        let ctx = pool.idle.lock().unwrap().pop();
+        let mut ctx = match ctx {
+            Some(ctx) => {
+                pool.reused.fetch_add(1, Ordering::Relaxed);
+                ctx
+            }
+            None => {
+                pool.created.fetch_add(1, Ordering::Relaxed);
+                ChainContext::new()
+            }
+        };
+        let mut req = req.unwrap_or_default();
+        let incoming = std::mem::take(&mut req.ctx);
+        ctx.data.extend(incoming.data);
+        ctx.typed.0.extend(incoming.typed.0);
+        ctx.deadline = incoming.deadline;
+        ctx.state = incoming.state;
+        ctx.more = incoming.more;
+        req.ctx = ctx;
+        Some(req)
+    }
+
+    // 交还用完的结果负载，其上下文放回池中供之后的请求复用；没有开启上下文池时直接丢弃
+    // 放回池中之前完全清空，避免数据泄漏到之后的请求
+    pub fn recycle(&self, payload: PayLoad) {
+        let Some(pool) = &self.context_pool else {
+            return;
+        };
+        let mut ctx = payload.ctx;
+        ctx.data.clear();
+        ctx.typed.0.clear();
+        ctx.deadline = None;
+        ctx.state = None;
+        ctx.more = false;
+        let mut idle = pool.idle.lock().unwrap();
+        if idle.len() < pool.max_idle {
+            idle.push(ctx);
+        }
+    }
+
+    // panic 隔离：处理函数的 panic 作为 LayerError::HandlerPanicked 错误返回，而不是继续展开
+    pub fn with_panic_isolation(mut self) -> Self {
+        self.panic_isolation = true;
+        for (index, layer) in self.layers().iter().enumerate() {
+            layer.write().unwrap().panic_isolation = Some(index);
+        }
+        self
+    }
+
+    // 从 head 到 tail 的所有层
+    fn layers(&self) -> Vec<SharedLayer> {
+        self.iter().collect()
+    }
+
+    // 沿 up_layer 从 head 走到 tail；每一步只短暂持有当前层的读锁，
+    // 上层已被释放（Weak 升级失败）时迭代结束
+    pub fn iter(&self) -> impl Iterator<Item = SharedLayer> {
+        std::iter::successors(self.head.clone(), |layer| {
+            layer.read().unwrap().up_layer.as_ref().and_then(|up| up.upgrade())
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.iter().count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.head.is_none()
+    }
+
+    // 设置链级别的共享状态，同一类型只保留最后一次设置的值
+    // 设置后进入链的每个负载都可以通过 ChainContext::state 读取
+    pub fn set_state<T: Any + Send + Sync>(&self, value: T) {
+        self.state.set(value);
+    }
+
+    pub fn state<T: Any + Send + Sync>(&self) -> Option<Arc<T>> {
+        self.state.get::<T>()
+    }
+
+    // 在负载上下文中挂上共享状态；没有状态时不改变请求
+    fn attach_state(&self, req: Option<PayLoad>) -> Option<PayLoad> {
+        if self.state.is_empty() {
+            return req;
         }
+        let mut req = req.unwrap_or_default();
+        req.ctx.state = Some(self.state.clone());
+        Some(req)
+    }
+
+    // 有序模式：入站负载带上递增序号，出站响应必须按序号顺序返回
+    pub fn with_ordered(mut self) -> Self {
+        self.ordered = Some((AtomicU64::new(0), AtomicU64::new(0)));
+        self
+    }
+
+    // 严格模式：拒绝从不处理该方向的端点层进入链的数据
+    pub fn with_strict_directions(mut self) -> Self {
+        self.strict_directions = true;
+        self
+    }
+
+    pub fn describe(&self) -> Vec<String> {
+        self.iter().map(|layer| layer.read().unwrap().describe()).collect()
+    }
+
+    // 从 head 到 tail 依次返回每一层的检查信息
+    pub fn inspect(&self) -> Vec<LayerInspection> {
+        self.iter().map(|layer| layer.read().unwrap().inspect()).collect()
     }
 
     pub fn add_layer(&mut self, layer: SharedLayer) {
+        if self.panic_isolation {
+            layer.write().unwrap().panic_isolation = Some(self.layers().len());
+        }
         match self.tail.take() {
             Some(tail) => {
                 // tail -> new layer
-                // tail.borrow_mut().up_layer = Some(layer.clone());
-                tail.borrow_mut().up_layer = Some(Arc::downgrade(&layer));
+                tail.write().unwrap().up_layer = Some(Arc::downgrade(&layer));
                 // new layer -> tail
-                layer.borrow_mut().lo_layer = Some(tail.clone());
+                layer.write().unwrap().lo_layer = Some(tail.clone());
                 self.tail = Some(layer);
             }
             None => {
-                layer.borrow_mut().lo_layer = None;
-                layer.borrow_mut().up_layer = None;
+                layer.write().unwrap().lo_layer = None;
+                layer.write().unwrap().up_layer = None;
                 self.head = Some(layer.clone());
                 self.tail = Some(layer);
             }
         }
     }
 
-    pub fn head(&self) -> Option<SharedLayer> {
-        self.head.clone()
+    // 给层命名后追加到 tail，之后可以用 find 按名字找回
+    pub fn add_named_layer(&mut self, name: impl Into<String>, layer: SharedLayer) {
+        layer.write().unwrap().name = Some(name.into());
+        self.add_layer(layer);
     }
 
-    pub fn tail(&self) -> Option<SharedLayer> {
-        self.tail.clone()
+    // 从 head 开始第一个名字匹配的层
+    pub fn find(&self, name: &str) -> Option<SharedLayer> {
+        self.iter().find(|layer| layer.read().unwrap().name.as_deref() == Some(name))
     }
 
-    pub fn handle_inbound(&self, req: Option<PayLoad>) -> Result<LayerResult, String>  {
-        if self.head.is_none() {
-            return Err("No layers in the chain".into());
+    // 关闭前让各层吐出缓冲的数据：按出站顺序从 tail 到 head 调用 flush 钩子，
+    // 吐出的数据会经过下面各层的出站处理；没有数据或处理出错的结果被丢弃
+    pub fn flush(&self) -> Vec<LayerResult> {
+        let mut layers = self.layers();
+        layers.reverse();
+        layers
+            .iter()
+            .filter_map(|layer| Hop::of(layer).flush())
+            .filter_map(Result::ok)
+            .filter(|result| result.data.is_some())
+            .collect()
+    }
+
+    // 移除第 index 层（从 head 开始计数）并重新连接相邻的层；越界时返回 None
+    pub fn remove_layer(&mut self, index: usize) -> Option<SharedLayer> {
+        let mut layers = self.layers();
+        if index >= layers.len() {
+            return None;
+        }
+        let removed = layers.remove(index);
+        {
+            let mut layer = removed.write().unwrap();
+            layer.lo_layer = None;
+            layer.up_layer = None;
+            layer.panic_isolation = None;
         }
+        self.relink(layers);
+        Some(removed)
+    }
 
-        let head = self.head.clone().unwrap();
-        let result = head.borrow().handle_inbound(req);
-        result
+    // 在第 index 层的位置插入新层，原来的层及其后面的层依次上移；越界时追加到 tail
+    pub fn insert_layer(&mut self, index: usize, layer: SharedLayer) {
+        let mut layers = self.layers();
+        let index = index.min(layers.len());
+        layers.insert(index, layer);
+        self.relink(layers);
     }
 
-    pub fn handle_outbound(&self, req: Option<PayLoad>) -> Result<LayerResult, String> {
-        if self.tail.is_none() {
-            return Err("No layers in the chain".into());
+    // 按给定顺序重新设置每一层的上下连接以及 head/tail
+    fn relink(&mut self, layers: Vec<SharedLayer>) {
+        for (index, layer) in layers.iter().enumerate() {
+            let mut current = layer.write().unwrap();
+            current.lo_layer = index.checked_sub(1).map(|below| layers[below].clone());
+            current.up_layer = layers.get(index + 1).map(Arc::downgrade);
+            if self.panic_isolation {
+                current.panic_isolation = Some(index);
+            }
         }
-        let tail = self.tail.clone().unwrap();
-        let result = tail.borrow().handle_outbound(req);
-        result
+        self.head = layers.first().cloned();
+        self.tail = layers.last().cloned();
     }
-}
 
-impl Drop for LayerChain {
-    fn drop(&mut self) {
-        self.head = None;
-        self.tail = None;
+    pub fn head(&self) -> Option<SharedLayer> {
+        self.head.clone()
+    }
+
+    pub fn tail(&self) -> Option<SharedLayer> {
+        self.tail.clone()
+    }
+
+    pub fn handle_inbound(&self, req: Option<PayLoad>) -> Result<LayerResult, LayerError>  {
+        let (head, req) = self.prepare_inbound(req)?;
+        let mut result = Hop::of(&head).handle(req, true)?;
+        mark_paused_layer(&mut result, &head);
+        Ok(result)
+    }
+
+    // 分块处理入站流：除最后一块外都标记 more，每一块按顺序单独经过整条链，
+    // 返回的迭代器是惰性的，调用者取一个结果才处理一块，不需要缓存整个流
+    pub fn stream_inbound<'a, I>(&'a self, chunks: I) -> impl Iterator<Item = Result<LayerResult, LayerError>> + 'a
+    where
+        I: IntoIterator<Item = PayLoad>,
+        I::IntoIter: 'a,
+    {
+        mark_chunks(chunks.into_iter()).map(move |chunk| self.handle_inbound(Some(chunk)))
+    }
+
+    // 出站方向的 stream_inbound
+    pub fn stream_outbound<'a, I>(&'a self, chunks: I) -> impl Iterator<Item = Result<LayerResult, LayerError>> + 'a
+    where
+        I: IntoIterator<Item = PayLoad>,
+        I::IntoIter: 'a,
+    {
+        mark_chunks(chunks.into_iter()).map(move |chunk| self.handle_outbound(Some(chunk)))
+    }
+
+    // 在工作线程上处理入站请求，超过 timeout 返回 LayerError::Timeout
+    // 处理函数无法被中断：超时后它仍会在后台运行到结束，结果被丢弃；
+    // 它运行期间不持有层的锁，链仍然可以增删层或修改层的设置
+    pub fn handle_inbound_timeout(&self, req: Option<PayLoad>, timeout: Duration) -> Result<LayerResult, LayerError> {
+        let (head, req) = self.prepare_inbound(req)?;
+        let mut result = run_with_timeout(&head, timeout, move |hop| hop.handle(req, true))?;
+        mark_paused_layer(&mut result, &head);
+        Ok(result)
+    }
+
+    // 出站方向的 handle_inbound_timeout
+    pub fn handle_outbound_timeout(&self, req: Option<PayLoad>, timeout: Duration) -> Result<LayerResult, LayerError> {
+        let (tail, req) = self.prepare_outbound(req)?;
+        let mut result = run_with_timeout(&tail, timeout, move |hop| hop.handle(req, false))?;
+        mark_paused_layer(&mut result, &tail);
+        self.check_sequence(&result)?;
+        Ok(result)
+    }
+
+    // 入站处理前的检查和上下文准备，返回链头和处理后的请求
+    fn prepare_inbound(&self, req: Option<PayLoad>) -> Result<(SharedLayer, Option<PayLoad>), LayerError> {
+        if self.head.is_none() {
+            return Err(LayerError::EmptyChain);
+        }
+
+        let head = self.head.clone().unwrap();
+        if self.strict_directions && head.read().unwrap().kind == LayerKind::OutboundOnly {
+            return Err(LayerError::DirectionRejected { inbound: true });
+        }
+        let req = self.pooled(req);
+        let req = match &self.ordered {
+            Some((next, _)) => {
+                let mut req = req.unwrap_or_default();
+                let seq = next.fetch_add(1, Ordering::Relaxed);
+                req.ctx.data.insert(SEQUENCE_KEY.to_string(), seq.to_string());
+                Some(req)
+            }
+            None => req,
+        };
+        let req = self.attach_state(req);
+        Ok((head, req))
+    }
+
+    // 入站处理前在上下文中写入追踪 id；已经带有追踪 id 的请求保持不变
+    pub fn handle_inbound_traced(&self, req: Option<PayLoad>) -> Result<LayerResult, LayerError> {
+        let mut req = req.unwrap_or_default();
+        if req.ctx.trace_id().is_none() {
+            req.ctx.set_trace_id(new_trace_id());
+        }
+        self.handle_inbound(Some(req))
+    }
+
+    pub fn handle_outbound(&self, req: Option<PayLoad>) -> Result<LayerResult, LayerError> {
+        let (tail, req) = self.prepare_outbound(req)?;
+        let mut result = Hop::of(&tail).handle(req, false)?;
+        mark_paused_layer(&mut result, &tail);
+        self.check_sequence(&result)?;
+        Ok(result)
+    }
+
+    fn prepare_outbound(&self, req: Option<PayLoad>) -> Result<(SharedLayer, Option<PayLoad>), LayerError> {
+        if self.tail.is_none() {
+            return Err(LayerError::EmptyChain);
+        }
+        let tail = self.tail.clone().unwrap();
+        if self.strict_directions && tail.read().unwrap().kind == LayerKind::InboundOnly {
+            return Err(LayerError::DirectionRejected { inbound: false });
+        }
+        let req = self.pooled(req);
+        Ok((tail, self.attach_state(req)))
+    }
+
+    // 检查离开链的出站响应是否按序；没有序号的负载不参与检查
+    fn check_sequence(&self, result: &LayerResult) -> Result<(), LayerError> {
+        let Some((_, expected)) = &self.ordered else {
+            return Ok(());
+        };
+        if result.is_pending() {
+            return Ok(());
+        }
+        let got = result
+            .data
+            .as_ref()
+            .and_then(|payload| payload.ctx.data.get(SEQUENCE_KEY))
+            .and_then(|seq| seq.parse::<u64>().ok());
+        match got {
+            Some(got) if got != expected.load(Ordering::Relaxed) => {
+                Err(LayerError::OutOfOrder { expected: expected.load(Ordering::Relaxed), got })
+            }
+            Some(got) => {
+                expected.store(got + 1, Ordering::Relaxed);
+                Ok(())
+            }
+            None => Ok(()),
+        }
+    }
+
+    // 从暂停的层继续处理：用新到达的数据再次调用该层同一方向的处理函数
+    pub fn resume(&self, token: ResumeToken, more: Option<PayLoad>) -> Result<LayerResult, LayerError> {
+        let layer = token
+            .layer
+            .as_ref()
+            .and_then(|layer| layer.upgrade())
+            .ok_or(LayerError::PausedLayerDropped)?;
+        let more = self.attach_state(self.pooled(more));
+        let mut result = Hop::of(&layer).handle(more, token.inbound)?;
+        mark_paused_layer(&mut result, &layer);
+        Ok(result)
+    }
+
+    // 将本链的出站连接到另一条链的入站，便于进程内端到端测试
+    pub fn connect(self, other: LayerChain) -> ConnectedChains {
+        ConnectedChains {
+            local: self,
+            remote: other,
+        }
+    }
+
+    // 把整条链当作一个入站方向的 Service，便于和其他服务组合
+    pub fn into_inbound_service(self) -> ChainService {
+        ChainService { chain: self, inbound: true }
+    }
+
+    // 出站方向的对应版本
+    pub fn into_outbound_service(self) -> ChainService {
+        ChainService { chain: self, inbound: false }
+    }
+}
+
+pub struct ChainService {
+    chain: LayerChain,
+    inbound: bool,
+}
+
+impl Service<Option<PayLoad>, Result<LayerResult, LayerError>> for ChainService {
+    fn call(&self, req: Option<PayLoad>) -> Result<LayerResult, LayerError> {
+        if self.inbound {
+            self.chain.handle_inbound(req)
+        } else {
+            self.chain.handle_outbound(req)
+        }
+    }
+}
+
+pub struct ConnectedChains {
+    local: LayerChain,
+    remote: LayerChain,
+}
+
+impl ConnectedChains {
+    pub fn local(&self) -> &LayerChain {
+        &self.local
+    }
+
+    pub fn remote(&self) -> &LayerChain {
+        &self.remote
+    }
+
+    pub fn round_trip(&self, req: Option<PayLoad>) -> Result<LayerResult, LayerError> {
+        let sent = self.local.handle_outbound(req)?;
+        if sent.is_pending() {
+            return Ok(sent);
+        }
+        self.remote.handle_inbound(sent.data)
+    }
+}
+
+// 测试用：包装一条链，记录每次进入链的 (方向, 负载) 以及链的输出，可在新建的链上回放并比较
+pub struct LayerChainTestHarness {
+    chain: LayerChain,
+    calls: Vec<RecordedCall>,
+}
+
+#[derive(Debug, Clone)]
+struct RecordedCall {
+    inbound: bool,
+    request: Option<PayLoad>,
+    result: Result<LayerResult, LayerError>,
+}
+
+impl LayerChainTestHarness {
+    pub fn new(chain: LayerChain) -> Self {
+        Self { chain, calls: Vec::new() }
+    }
+
+    pub fn chain(&self) -> &LayerChain {
+        &self.chain
+    }
+
+    pub fn handle_inbound(&mut self, req: Option<PayLoad>) -> Result<LayerResult, LayerError> {
+        self.record(true, req)
+    }
+
+    pub fn handle_outbound(&mut self, req: Option<PayLoad>) -> Result<LayerResult, LayerError> {
+        self.record(false, req)
+    }
+
+    fn record(&mut self, inbound: bool, req: Option<PayLoad>) -> Result<LayerResult, LayerError> {
+        let result = if inbound {
+            self.chain.handle_inbound(req.clone())
+        } else {
+            self.chain.handle_outbound(req.clone())
+        };
+        self.calls.push(RecordedCall { inbound, request: req, result: result.clone() });
+        result
+    }
+
+    // 按顺序返回进入链和离开链的 (方向, 负载)；出错的调用只有输入
+    pub fn transcript(&self) -> Vec<(Direction, PayLoad)> {
+        let mut transcript = Vec::new();
+        for call in &self.calls {
+            let direction = if call.inbound { Direction::Inbound } else { Direction::Outbound };
+            transcript.push((direction, call.request.clone().unwrap_or_default()));
+            if let Ok(result) = &call.result {
+                transcript.push((result.direction.clone(), result.data.clone().unwrap_or_default()));
+            }
+        }
+        transcript
+    }
+
+    // 在新建的链上按顺序重放记录的输入，输出或错误不一致时返回第一个差异
+    // 负载比较 data 和上下文中除追踪 id 以外的键值
+    pub fn replay(&self, chain: LayerChain) -> Result<LayerChainTestHarness, String> {
+        let mut replayed = LayerChainTestHarness::new(chain);
+        for (index, call) in self.calls.iter().enumerate() {
+            let result = replayed.record(call.inbound, call.request.clone());
+            let same = match (&call.result, &result) {
+                (Ok(expected), Ok(got)) => same_flow(
+                    (&expected.direction, &expected.data),
+                    (&got.direction, &got.data),
+                ),
+                (Err(expected), Err(got)) => expected == got,
+                _ => false,
+            };
+            if !same {
+                return Err(format!(
+                    "replay diverged at call {}: expected {:?}, got {:?}",
+                    index, call.result, result
+                ));
+            }
+        }
+        Ok(replayed)
+    }
+}
+
+fn same_flow(a: (&Direction, &Option<PayLoad>), b: (&Direction, &Option<PayLoad>)) -> bool {
+    let comparable = |payload: &Option<PayLoad>| {
+        payload.as_ref().map(|payload| {
+            let mut data = payload.ctx.data.clone();
+            data.remove(TRACE_ID_KEY);
+            (payload.data.clone(), payload.bytes.clone(), data)
+        })
+    };
+    std::mem::discriminant(a.0) == std::mem::discriminant(b.0) && comparable(a.1) == comparable(b.1)
+}
+
+impl Drop for LayerChain {
+    fn drop(&mut self) {
+        self.head = None;
+        self.tail = None;
     }
 }
 
@@ -257,11 +1223,45 @@ mod tests {
         let chain = LayerChain::new();
         let req = PayLoad {
             data: Some("test".to_string()),
-            ctx: None,
+            bytes: None,
+            ctx: ChainContext::new(),
         };
         
-        assert!(chain.handle_inbound(Some(req.clone())).is_err());
-        assert!(chain.handle_outbound(Some(req)).is_err());
+        assert_eq!(chain.handle_inbound(Some(req.clone())).unwrap_err(), LayerError::EmptyChain);
+        assert_eq!(chain.handle_outbound(Some(req)).unwrap_err(), LayerError::EmptyChain);
+    }
+
+    #[test]
+    fn test_structured_errors() {
+        // 处理函数的错误原样返回
+        let failing = Layer::inbound_only(|_| Err("bad checksum".into()));
+        let mut chain = LayerChain::new();
+        chain.add_layer(Layer::inbound_only(|req| Ok(LayerResult { direction: Direction::Inbound, data: req })));
+        chain.add_layer(failing);
+        assert_eq!(
+            chain.handle_inbound(Some(PayLoad::empty())).unwrap_err(),
+            LayerError::HandlerFailed("bad checksum".to_string())
+        );
+
+        // 链被丢弃后，head 的上层随之释放
+        let head = chain.head().unwrap();
+        drop(chain);
+        assert_eq!(
+            head.read().unwrap().handle_inbound(Some(PayLoad::empty())).unwrap_err(),
+            LayerError::UpstreamDropped
+        );
+
+        let mut strict = LayerChain::new().with_strict_directions();
+        strict.add_layer(Layer::inbound_only(|req| Ok(LayerResult { direction: Direction::Inbound, data: req })));
+        assert_eq!(
+            strict.handle_outbound(Some(PayLoad::empty())).unwrap_err(),
+            LayerError::DirectionRejected { inbound: false }
+        );
+
+        assert_eq!(
+            strict.resume(ResumeToken::default(), None).unwrap_err(),
+            LayerError::PausedLayerDropped
+        );
     }
 
     #[test]
@@ -274,6 +1274,7 @@ mod tests {
                     direction: Direction::Inbound,
                     data: Some(PayLoad {
                         data: req.data,
+                        bytes: req.bytes,
                         ctx:  req.ctx,
                     }),
                 })
@@ -285,6 +1286,7 @@ mod tests {
                     direction: Direction::Outbound,
                     data: Some(PayLoad {
                         data: req.data,
+                        bytes: req.bytes,
                         ctx:  req.ctx,
                     }),
                 })
@@ -296,13 +1298,841 @@ mod tests {
         
         let req = PayLoad {
             data: Some("test".to_string()),
-            ctx: None,
+            bytes: None,
+            ctx: ChainContext::new(),
         };
         
         assert!(chain.handle_inbound(Some(req.clone())).is_ok());
         assert!(chain.handle_outbound(Some(req)).is_ok());
     }
 
+    #[test]
+    fn test_connected_chains_round_trip() {
+        let client = LayerBuilder::new()
+            .with_inbound_fn(|req| Ok(LayerResult { direction: Direction::Inbound, data: req }))
+            .with_outbound_fn(|req| {
+                let req = req.unwrap();
+                Ok(LayerResult {
+                    direction: Direction::Outbound,
+                    data: Some(PayLoad {
+                        data: req.data.map(|d| format!("{}|client-out", d)),
+                        bytes: req.bytes,
+                        ctx: req.ctx,
+                    }),
+                })
+            })
+            .build().unwrap();
+        let server = LayerBuilder::new()
+            .with_inbound_fn(|req| {
+                let req = req.unwrap();
+                Ok(LayerResult {
+                    direction: Direction::Inbound,
+                    data: Some(PayLoad {
+                        data: req.data.map(|d| format!("{}|server-in", d)),
+                        bytes: req.bytes,
+                        ctx: req.ctx,
+                    }),
+                })
+            })
+            .with_outbound_fn(|req| Ok(LayerResult { direction: Direction::Outbound, data: req }))
+            .build().unwrap();
+
+        let mut client_chain = LayerChain::new();
+        client_chain.add_layer(client);
+        let mut server_chain = LayerChain::new();
+        server_chain.add_layer(server);
+        let connected = client_chain.connect(server_chain);
+
+        let req = PayLoad {
+            data: Some("hello".to_string()),
+            bytes: None,
+            ctx: ChainContext::new(),
+        };
+        let result = connected.round_trip(Some(req)).unwrap();
+        assert_eq!(result.data.unwrap().data.unwrap(), "hello|client-out|server-in");
+    }
+
+    #[test]
+    fn test_payload_constructors() {
+        let empty = PayLoad::empty();
+        assert!(empty.data.is_none());
+        assert!(empty.ctx.data.is_empty());
+
+        let payload = PayLoad::of("hello".to_string());
+        assert_eq!(payload.data.as_deref(), Some("hello"));
+        assert!(payload.ctx.data.is_empty());
+
+        let mut ctx = ChainContext::new();
+        ctx.data.insert("k".to_string(), "v".to_string());
+        let payload = PayLoad::with_ctx("hello".to_string(), ctx);
+        assert_eq!(payload.ctx.data.get("k").map(String::as_str), Some("v"));
+    }
+
+    #[test]
+    fn test_binary_payload_round_trip() {
+        let frame = vec![0xff, 0x00, 0xfe, 0x80, 0x0a];
+        let layer = LayerBuilder::new()
+            .with_inbound_fn(|req| Ok(LayerResult { direction: Direction::Inbound, data: req }))
+            .with_outbound_passthrough()
+            .build().unwrap();
+        let mut chain = LayerChain::new();
+        chain.add_layer(layer);
+
+        let inbound = chain.handle_inbound(Some(PayLoad::bytes(frame.clone()))).unwrap();
+        let payload = inbound.data.unwrap();
+        assert_eq!(payload.bytes.as_deref(), Some(&frame[..]));
+        assert!(payload.as_text().is_none());
+
+        let outbound = chain.handle_outbound(Some(payload)).unwrap();
+        assert_eq!(outbound.data.unwrap().as_bytes(), Some(&frame[..]));
+
+        let text = PayLoad::text("hi");
+        assert_eq!(text.as_text(), Some("hi"));
+        assert_eq!(text.as_bytes(), Some(&b"hi"[..]));
+    }
+
+    #[test]
+    fn test_handlers_share_context_without_none() {
+        let layer0 = LayerBuilder::new()
+            .with_inbound_fn(|req| {
+                let mut req = req.unwrap();
+                req.ctx.data.insert("seen_by".to_string(), "layer0".to_string());
+                Ok(LayerResult { direction: Direction::Inbound, data: Some(req) })
+            })
+            .with_outbound_fn(|req| Ok(LayerResult { direction: Direction::Outbound, data: req }))
+            .build().unwrap();
+        let layer1 = LayerBuilder::new()
+            .with_inbound_fn(|req| {
+                let mut req = req.unwrap();
+                let seen = req.ctx.data.get("seen_by").cloned().unwrap_or_default();
+                req.ctx.data.insert("seen_by".to_string(), format!("{},layer1", seen));
+                Ok(LayerResult { direction: Direction::Inbound, data: Some(req) })
+            })
+            .with_outbound_fn(|req| Ok(LayerResult { direction: Direction::Outbound, data: req }))
+            .build().unwrap();
+
+        let mut chain = LayerChain::new();
+        chain.add_layer(layer0);
+        chain.add_layer(layer1);
+
+        let result = chain.handle_inbound(Some(PayLoad::empty())).unwrap();
+        let ctx = result.data.unwrap().ctx;
+        assert_eq!(ctx.data.get("seen_by").map(String::as_str), Some("layer0,layer1"));
+    }
+
+    #[test]
+    fn test_chain_state_shared_across_requests() {
+        struct Counter(AtomicU64);
+        let counting = |req: Option<PayLoad>, direction: Direction| {
+            let req = req.unwrap();
+            req.ctx.state::<Counter>().ok_or("missing counter")?.0.fetch_add(1, Ordering::SeqCst);
+            Ok(LayerResult { direction, data: Some(req) })
+        };
+        let layer0 = LayerBuilder::new()
+            .with_inbound_fn(move |req| counting(req, Direction::Inbound))
+            .with_outbound_passthrough()
+            .build().unwrap();
+        let layer1 = LayerBuilder::new()
+            .with_inbound_fn(move |req| counting(req, Direction::Inbound))
+            .with_outbound_fn(move |req| counting(req, Direction::Outbound))
+            .build().unwrap();
+
+        let mut chain = LayerChain::new();
+        chain.add_layer(layer0);
+        chain.add_layer(layer1);
+        chain.set_state(Counter(AtomicU64::new(0)));
+
+        // 没有负载的请求也能拿到共享状态
+        chain.handle_inbound(None).unwrap();
+        chain.handle_inbound(Some(PayLoad::of("a".to_string()))).unwrap();
+        chain.handle_outbound(None).unwrap();
+        assert_eq!(chain.state::<Counter>().unwrap().0.load(Ordering::SeqCst), 5);
+        assert!(chain.state::<String>().is_none());
+    }
+
+    #[test]
+    fn test_typed_context_values() {
+        #[derive(Debug, PartialEq)]
+        struct ConnectionId(u64);
+
+        let mut chain = LayerChain::new();
+        chain.add_layer(Layer::inbound_only(|req| {
+            let mut req = req.unwrap();
+            req.ctx.insert(ConnectionId(7));
+            req.ctx.insert(42u32);
+            req.ctx.data.insert("conn".to_string(), "text".to_string());
+            Ok(LayerResult { direction: Direction::Inbound, data: Some(req) })
+        }));
+        chain.add_layer(Layer::inbound_only(|req| {
+            let req = req.unwrap();
+            // 下一层按类型取回上一层写入的值
+            assert_eq!(req.ctx.get::<ConnectionId>(), Some(&ConnectionId(7)));
+            assert_eq!(req.ctx.get::<u32>(), Some(&42));
+            Ok(LayerResult { direction: Direction::Inbound, data: Some(req) })
+        }));
+
+        let result = chain.handle_inbound(Some(PayLoad::text("frame"))).unwrap();
+        let mut ctx = result.data.unwrap().ctx;
+        assert_eq!(ctx.get::<u64>(), None);
+        assert_eq!(ctx.data.get("conn").map(String::as_str), Some("text"));
+
+        ctx.insert(43u32);
+        assert_eq!(ctx.get::<u32>(), Some(&43));
+        assert_eq!(ctx.get::<ConnectionId>(), Some(&ConnectionId(7)));
+    }
+
+    #[test]
+    fn test_panic_isolation() {
+        let build = |panics: bool| {
+            LayerBuilder::new()
+                .with_inbound_fn(move |req| {
+                    if panics {
+                        panic!("bad frame");
+                    }
+                    Ok(LayerResult { direction: Direction::Inbound, data: req })
+                })
+                .with_outbound_passthrough()
+                .build().unwrap()
+        };
+
+        let mut chain = LayerChain::new().with_panic_isolation();
+        chain.add_layer(build(false));
+        chain.add_layer(build(true));
+        chain.add_layer(build(false));
+
+        let err = chain.handle_inbound(Some(PayLoad::empty())).unwrap_err();
+        assert_eq!(err, LayerError::HandlerPanicked { layer_index: 1, message: "bad frame".to_string() });
+        // 链仍然可用，之后的请求同样返回错误而不是展开
+        assert!(chain.handle_inbound(Some(PayLoad::empty())).is_err());
+        assert!(chain.handle_outbound(Some(PayLoad::empty())).is_ok());
+
+        // 先添加层再开启隔离也会生效
+        let mut chain = LayerChain::new();
+        chain.add_layer(build(true));
+        let chain = chain.with_panic_isolation();
+        assert_eq!(
+            chain.handle_inbound(None).unwrap_err().to_string(),
+            "handler of layer 0 panicked: bad frame"
+        );
+    }
+
+    #[test]
+    fn test_harness_record_and_replay() {
+        let build_chain = |suffix: &'static str| {
+            let framing = LayerBuilder::new()
+                .with_inbound_fn(|req| {
+                    let mut req = req.unwrap_or_default();
+                    req.data = req.data.map(|d| d.trim_matches(|c| c == '[' || c == ']').to_string());
+                    Ok(LayerResult { direction: Direction::Inbound, data: Some(req) })
+                })
+                .with_outbound_fn(|req| {
+                    let mut req = req.unwrap_or_default();
+                    req.data = req.data.map(|d| format!("[{}]", d));
+                    Ok(LayerResult { direction: Direction::Outbound, data: Some(req) })
+                })
+                .build().unwrap();
+            let app = LayerBuilder::new()
+                .with_inbound_fn(move |req| {
+                    let mut req = req.unwrap_or_default();
+                    req.ctx.data.insert("handled".to_string(), suffix.to_string());
+                    Ok(LayerResult { direction: Direction::Inbound, data: Some(req) })
+                })
+                .with_outbound_passthrough()
+                .build().unwrap();
+            let mut chain = LayerChain::new();
+            chain.add_layer(framing);
+            chain.add_layer(app);
+            chain
+        };
+
+        let mut harness = LayerChainTestHarness::new(build_chain("v1"));
+        harness.handle_inbound(Some(PayLoad::of("[hello]".to_string()))).unwrap();
+        harness.handle_outbound(Some(PayLoad::of("world".to_string()))).unwrap();
+        harness.handle_inbound(None).unwrap();
+
+        let transcript = harness.transcript();
+        assert_eq!(transcript.len(), 6);
+        assert_eq!(transcript[1].1.data.as_deref(), Some("hello"));
+        assert_eq!(transcript[3].1.data.as_deref(), Some("[world]"));
+
+        let replayed = harness.replay(build_chain("v1")).unwrap();
+        assert_eq!(format!("{:?}", replayed.transcript()), format!("{:?}", transcript));
+
+        // 行为变化的链回放时报告差异
+        let err = harness.replay(build_chain("v2")).err().unwrap();
+        assert!(err.starts_with("replay diverged at call 0"), "{}", err);
+    }
+
+    #[test]
+    fn test_chain_handles_inbound_on_another_thread() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<Layer>();
+        assert_send_sync::<LayerChain>();
+
+        let mut chain = LayerChain::new();
+        chain.add_layer(Layer::inbound_only(|req| Ok(LayerResult { direction: Direction::Inbound, data: req })));
+        chain.add_layer(Layer::inbound_only(|req| {
+            let req = req.unwrap();
+            Ok(LayerResult {
+                direction: Direction::Inbound,
+                data: Some(PayLoad::with_ctx(req.data.unwrap().to_uppercase(), req.ctx)),
+            })
+        }));
+        let chain = Arc::new(chain);
+
+        let worker = {
+            let chain = chain.clone();
+            std::thread::spawn(move || chain.handle_inbound(Some(PayLoad::of("hello".to_string()))))
+        };
+        let result = worker.join().unwrap().unwrap();
+        assert_eq!(result.data.unwrap().data.as_deref(), Some("HELLO"));
+    }
+
+    #[test]
+    fn test_bounced_request_while_layer_is_relinked() {
+        // bottom 同时属于另一条链，另一条链在请求折返途中追加层，需要 bottom 的写锁
+        let bottom = LayerBuilder::new().with_inbound_passthrough().with_outbound_passthrough().build().unwrap();
+        let mut other = LayerChain::new();
+        other.add_layer(bottom.clone());
+        let other = Arc::new(Mutex::new(Some(other)));
+        let (relinked_tx, relinked_rx) = std::sync::mpsc::channel();
+        let bounce = LayerBuilder::new()
+            .with_inbound_fn(move |req| {
+                let mut other = other.lock().unwrap().take().unwrap();
+                let relinked_tx = relinked_tx.clone();
+                std::thread::spawn(move || {
+                    other.add_layer(Layer::inbound_only(|req| Ok(LayerResult { direction: Direction::Inbound, data: req })));
+                    let _ = relinked_tx.send(());
+                });
+                // 等待写锁排队后再把请求折返给 bottom
+                std::thread::sleep(Duration::from_millis(100));
+                Ok(LayerResult { direction: Direction::Outbound, data: req })
+            })
+            .with_outbound_passthrough()
+            .build()
+            .unwrap();
+        let mut chain = LayerChain::new();
+        chain.add_layer(bottom);
+        chain.add_layer(bounce);
+        let chain = Arc::new(chain);
+
+        let (result_tx, result_rx) = std::sync::mpsc::channel();
+        {
+            let chain = chain.clone();
+            std::thread::spawn(move || {
+                let _ = result_tx.send(chain.handle_inbound(Some(PayLoad::text("ping"))));
+            });
+        }
+        let result = result_rx.recv_timeout(Duration::from_secs(5)).expect("bounced request deadlocked").unwrap();
+        assert_eq!(result.data.unwrap().as_text(), Some("ping"));
+        relinked_rx.recv_timeout(Duration::from_secs(5)).expect("add_layer did not finish");
+    }
+
+    #[test]
+    fn test_handle_inbound_timeout() {
+        let finished = Arc::new(std::sync::atomic::AtomicBool::new(false));
+        let done = finished.clone();
+        let mut chain = LayerChain::new();
+        chain.add_layer(Layer::inbound_only(move |req| {
+            // 模拟卡住的处理函数
+            if req.as_ref().and_then(|r| r.data.as_deref()) == Some("slow") {
+                std::thread::sleep(Duration::from_millis(300));
+                done.store(true, Ordering::SeqCst);
+            }
+            Ok(LayerResult { direction: Direction::Inbound, data: req })
+        }));
+
+        let started = Instant::now();
+        let err = chain.handle_inbound_timeout(Some(PayLoad::text("slow")), Duration::from_millis(50)).unwrap_err();
+        assert_eq!(err, LayerError::Timeout);
+        assert!(started.elapsed() < Duration::from_millis(300));
+
+        // 超时的处理函数仍在后台运行到结束
+        std::thread::sleep(Duration::from_millis(500));
+        assert!(finished.load(Ordering::SeqCst));
+
+        let result = chain.handle_inbound_timeout(Some(PayLoad::text("fast")), Duration::from_secs(5)).unwrap();
+        assert_eq!(result.data.unwrap().as_text(), Some("fast"));
+    }
+
+    #[test]
+    fn test_chain_mutable_after_timeout() {
+        let (release_tx, release_rx) = std::sync::mpsc::channel::<()>();
+        let release_rx = Mutex::new(release_rx);
+        let mut chain = LayerChain::new();
+        chain.add_layer(Layer::outbound_only(move |req| {
+            // 卡住直到测试结束
+            let _ = release_rx.lock().unwrap().recv();
+            Ok(LayerResult { direction: Direction::Outbound, data: req })
+        }));
+        chain.add_layer(Layer::inbound_only(|req| Ok(LayerResult { direction: Direction::Outbound, data: req })));
+
+        let err = chain.handle_inbound_timeout(Some(PayLoad::text("stuck")), Duration::from_millis(50)).unwrap_err();
+        assert_eq!(err, LayerError::Timeout);
+
+        // 卡住的处理函数仍在运行，两层都可以被修改
+        let (done_tx, done_rx) = std::sync::mpsc::channel();
+        std::thread::spawn(move || {
+            chain.add_layer(Layer::inbound_only(|req| Ok(LayerResult { direction: Direction::Inbound, data: req })));
+            chain.insert_layer(0, Layer::inbound_only(|req| Ok(LayerResult { direction: Direction::Inbound, data: req })));
+            chain = chain.with_panic_isolation();
+            let _ = done_tx.send(chain.len());
+        });
+        let len = done_rx.recv_timeout(Duration::from_secs(5)).expect("chain mutation blocked by a timed-out handler");
+        assert_eq!(len, 4);
+        release_tx.send(()).unwrap();
+    }
+
+    #[test]
+    fn test_remove_and_insert_layers() {
+        let tag = |tag: &'static str| {
+            LayerBuilder::new()
+                .with_name(tag)
+                .with_inbound_fn(move |req| {
+                    let mut req = req.unwrap_or_default();
+                    req.data = req.data.map(|d| format!("{}|{}", d, tag));
+                    Ok(LayerResult { direction: Direction::Inbound, data: Some(req) })
+                })
+                .with_outbound_passthrough()
+                .build().unwrap()
+        };
+        let inbound = |chain: &LayerChain| {
+            let result = chain.handle_inbound(Some(PayLoad::of("x".to_string()))).unwrap();
+            result.data.unwrap().data.unwrap()
+        };
+        let mut chain = LayerChain::new();
+        chain.add_layer(tag("a"));
+        chain.add_layer(tag("b"));
+        chain.add_layer(tag("c"));
+        assert_eq!(inbound(&chain), "x|a|b|c");
+
+        let removed = chain.remove_layer(1).unwrap();
+        assert_eq!(removed.read().unwrap().name.as_deref(), Some("b"));
+        assert!(removed.read().unwrap().lo_layer.is_none());
+        assert_eq!(inbound(&chain), "x|a|c");
+        assert!(chain.remove_layer(2).is_none());
+
+        chain.insert_layer(0, tag("z"));
+        chain.insert_layer(2, removed);
+        chain.insert_layer(10, tag("end"));
+        assert_eq!(inbound(&chain), "x|z|a|b|c|end");
+
+        chain.remove_layer(0);
+        chain.remove_layer(3);
+        assert_eq!(inbound(&chain), "x|a|b|c");
+        assert_eq!(chain.head().unwrap().read().unwrap().name.as_deref(), Some("a"));
+        assert_eq!(chain.tail().unwrap().read().unwrap().name.as_deref(), Some("c"));
+    }
+
+    #[test]
+    fn test_chain_len_and_iter() {
+        let mut chain = LayerChain::new();
+        assert!(chain.is_empty());
+        assert_eq!(chain.len(), 0);
+        assert_eq!(chain.iter().count(), 0);
+
+        for name in ["a", "b", "c"] {
+            chain.add_layer(LayerBuilder::new().with_name(name).with_inbound_passthrough().with_outbound_passthrough().build().unwrap());
+        }
+        assert!(!chain.is_empty());
+        assert_eq!(chain.len(), 3);
+        assert_eq!(chain.iter().count(), 3);
+        let names = chain.iter().map(|layer| layer.read().unwrap().name.clone().unwrap()).collect::<Vec<_>>();
+        assert_eq!(names, ["a", "b", "c"]);
+    }
+
+    #[test]
+    fn test_find_named_layer() {
+        let passthrough = || LayerBuilder::new().with_inbound_passthrough().with_outbound_passthrough();
+        let mut chain = LayerChain::new();
+        chain.add_named_layer("framing", passthrough().build().unwrap());
+        chain.add_layer(passthrough().with_name("compression").with_codec("gzip").build().unwrap());
+
+        let compression = chain.find("compression").unwrap();
+        assert_eq!(compression.read().unwrap().codec.as_deref(), Some("gzip"));
+        assert!(Arc::ptr_eq(&chain.find("framing").unwrap(), &chain.head().unwrap()));
+        assert!(chain.find("tls").is_none());
+    }
+
+    #[test]
+    fn test_stream_inbound_chunks() {
+        // 转换层逐块处理，不缓存整个流
+        let upper = Layer::inbound_only(|req| {
+            let mut req = req.unwrap_or_default();
+            req.data = req.data.map(|d| d.to_uppercase());
+            Ok(LayerResult { direction: Direction::Inbound, data: Some(req) })
+        });
+        let mut chain = LayerChain::new();
+        chain.add_layer(upper);
+
+        let chunks = ["alpha", "beta", "gamma"].iter().map(|c| PayLoad::text(*c));
+        let results = chain
+            .stream_inbound(chunks)
+            .map(|result| {
+                let payload = result.unwrap().data.unwrap();
+                (payload.has_more(), payload.data.unwrap())
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(results, vec![
+            (true, "ALPHA".to_string()),
+            (true, "BETA".to_string()),
+            (false, "GAMMA".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn test_flush_drains_buffered_frames() {
+        // 出站数据先缓存，凑满 3 个才一起发出
+        let buffer = Arc::new(Mutex::new(Vec::<String>::new()));
+        let pending = buffer.clone();
+        let drained = buffer.clone();
+        let batching = LayerBuilder::new()
+            .with_inbound_passthrough()
+            .with_outbound_fn(move |req| {
+                let mut pending = pending.lock().unwrap();
+                pending.extend(req.and_then(|r| r.data));
+                let data = match pending.len() >= 3 {
+                    true => Some(PayLoad::text(pending.drain(..).collect::<Vec<_>>().join("+"))),
+                    false => None,
+                };
+                Ok(LayerResult { direction: Direction::Outbound, data })
+            })
+            .with_flush_fn(move |_| {
+                let frames = drained.lock().unwrap().drain(..).collect::<Vec<_>>();
+                let data = match frames.is_empty() {
+                    true => None,
+                    false => Some(PayLoad::text(frames.join("+"))),
+                };
+                Ok(LayerResult { direction: Direction::Outbound, data })
+            })
+            .build().unwrap();
+        let framing = Layer::outbound_only(|req| {
+            let mut req = req.unwrap_or_default();
+            req.data = req.data.map(|d| format!("frame:{}", d));
+            Ok(LayerResult { direction: Direction::Outbound, data: Some(req) })
+        });
+
+        let mut chain = LayerChain::new();
+        chain.add_layer(framing);
+        chain.add_layer(batching);
+
+        for frame in ["a", "b"] {
+            let result = chain.handle_outbound(Some(PayLoad::text(frame))).unwrap();
+            assert_eq!(result.data.unwrap().data, None);
+        }
+        let flushed = chain.flush();
+        assert_eq!(flushed.len(), 1);
+        assert_eq!(flushed[0].data.as_ref().unwrap().as_text(), Some("frame:a+b"));
+        assert!(buffer.lock().unwrap().is_empty());
+
+        // 缓冲已经清空，再次 flush 不会产生结果
+        assert!(chain.flush().is_empty());
+    }
+
+    #[test]
+    fn test_chain_as_service() {
+        let tag = |tag: &'static str| {
+            LayerBuilder::new()
+                .with_inbound_fn(move |req| {
+                    let mut req = req.unwrap_or_default();
+                    req.data = req.data.map(|d| format!("{}|{}", d, tag));
+                    Ok(LayerResult { direction: Direction::Inbound, data: Some(req) })
+                })
+                .with_outbound_fn(move |req| {
+                    let mut req = req.unwrap_or_default();
+                    req.data = req.data.map(|d| format!("{}|{}", tag, d));
+                    Ok(LayerResult { direction: Direction::Outbound, data: Some(req) })
+                })
+                .build().unwrap()
+        };
+        let chain_of = || {
+            let mut chain = LayerChain::new();
+            chain.add_layer(tag("a"));
+            chain.add_layer(tag("b"));
+            chain
+        };
+
+        let inbound = chain_of().into_inbound_service();
+        let text = service(move |req: Option<PayLoad>| {
+            inbound.call(req).map(|result| result.data.and_then(|payload| payload.data))
+        });
+        assert_eq!(text.call(Some(PayLoad::of("x".to_string()))), Ok(Some("x|a|b".to_string())));
+
+        let outbound = chain_of().into_outbound_service();
+        let result = outbound.call(Some(PayLoad::of("x".to_string()))).unwrap();
+        assert_eq!(result.data.and_then(|payload| payload.data).as_deref(), Some("a|b|x"));
+
+        let empty = LayerChain::new().into_inbound_service();
+        assert_eq!(empty.call(None).unwrap_err(), LayerError::EmptyChain);
+    }
+
+    #[test]
+    fn test_inbound_only_layer() {
+        let framing = LayerBuilder::new()
+            .with_inbound_fn(|req| Ok(LayerResult { direction: Direction::Inbound, data: req }))
+            .with_outbound_fn(|req| {
+                let req = req.unwrap();
+                Ok(LayerResult {
+                    direction: Direction::Outbound,
+                    data: Some(PayLoad { data: req.data.map(|d| format!("[{}]", d)), bytes: req.bytes, ctx: req.ctx }),
+                })
+            })
+            .build().unwrap();
+        let audit = Layer::inbound_only(|req| {
+            let req = req.unwrap();
+            Ok(LayerResult {
+                direction: Direction::Inbound,
+                data: Some(PayLoad { data: req.data.map(|d| format!("audited:{}", d)), bytes: req.bytes, ctx: req.ctx }),
+            })
+        });
+
+        let mut chain = LayerChain::new();
+        chain.add_layer(framing.clone());
+        chain.add_layer(audit.clone());
+        assert_eq!(chain.describe(), vec!["duplex", "inbound-only (outbound passthrough)"]);
+
+        let inbound = chain.handle_inbound(Some(PayLoad::of("in".to_string()))).unwrap();
+        assert_eq!(inbound.data.unwrap().data.unwrap(), "audited:in");
+        let outbound = chain.handle_outbound(Some(PayLoad::of("out".to_string()))).unwrap();
+        assert_eq!(outbound.data.unwrap().data.unwrap(), "[out]");
+
+        let mut strict = LayerChain::new().with_strict_directions();
+        strict.add_layer(framing);
+        strict.add_layer(audit);
+        assert!(strict.handle_inbound(Some(PayLoad::of("in".to_string()))).is_ok());
+        assert!(strict.handle_outbound(Some(PayLoad::of("out".to_string()))).is_err());
+    }
+
+    #[test]
+    fn test_deadline_exceeded() {
+        use std::sync::atomic::{AtomicBool, Ordering};
+
+        let slow = LayerBuilder::new()
+            .with_inbound_fn(|req| {
+                std::thread::sleep(Duration::from_millis(50));
+                Ok(LayerResult { direction: Direction::Inbound, data: req })
+            })
+            .with_outbound_fn(|req| Ok(LayerResult { direction: Direction::Outbound, data: req }))
+            .build().unwrap();
+        let reached = Arc::new(AtomicBool::new(false));
+        let flag = reached.clone();
+        let next = LayerBuilder::new()
+            .with_inbound_fn(move |req| {
+                flag.store(true, Ordering::SeqCst);
+                Ok(LayerResult { direction: Direction::Inbound, data: req })
+            })
+            .with_outbound_fn(|req| Ok(LayerResult { direction: Direction::Outbound, data: req }))
+            .build().unwrap();
+
+        let mut chain = LayerChain::new();
+        chain.add_layer(slow);
+        chain.add_layer(next);
+
+        let mut req = PayLoad::of("hello".to_string());
+        req.ctx.set_timeout(Duration::from_millis(10));
+        let err = chain.handle_inbound(Some(req)).unwrap_err();
+        assert_eq!(err, LayerError::DeadlineExceeded);
+        assert!(!reached.load(Ordering::SeqCst));
+
+        let mut req = PayLoad::of("hello".to_string());
+        req.ctx.set_timeout(Duration::from_secs(5));
+        assert!(chain.handle_inbound(Some(req)).is_ok());
+        assert!(reached.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn test_trace_id_shared_within_request() {
+        let seen = Arc::new(Mutex::new(Vec::<String>::new()));
+        let mut chain = LayerChain::new();
+        for _ in 0..3 {
+            let seen = seen.clone();
+            let layer = LayerBuilder::new()
+                .with_inbound_fn(move |req| {
+                    let trace_id = req.as_ref().and_then(|r| r.ctx.trace_id()).unwrap();
+                    seen.lock().unwrap().push(trace_id.to_string());
+                    Ok(LayerResult { direction: Direction::Inbound, data: req })
+                })
+                .with_outbound_fn(|req| Ok(LayerResult { direction: Direction::Outbound, data: req }))
+                .build().unwrap();
+            chain.add_layer(layer);
+        }
+
+        let first = chain.handle_inbound_traced(Some(PayLoad::of("a".to_string()))).unwrap();
+        let first_id = first.data.unwrap().ctx.trace_id().unwrap().to_string();
+        assert_eq!(first_id.len(), 36);
+        assert_eq!(seen.lock().unwrap().as_slice(), [first_id.clone(), first_id.clone(), first_id.clone()]);
+
+        seen.lock().unwrap().clear();
+        chain.handle_inbound_traced(None).unwrap();
+        let second_id = seen.lock().unwrap()[0].clone();
+        assert_ne!(second_id, first_id);
+        assert!(seen.lock().unwrap().iter().all(|id| *id == second_id));
+
+        let mut ctx = ChainContext::new();
+        ctx.set_trace_id("given-id");
+        let given = chain.handle_inbound_traced(Some(PayLoad::with_ctx("b".to_string(), ctx))).unwrap();
+        assert_eq!(given.data.unwrap().ctx.trace_id(), Some("given-id"));
+    }
+
+    #[test]
+    fn test_context_pool_keeps_result_context() {
+        let mut chain = LayerChain::new();
+        chain.add_layer(Layer::inbound_only(|req| Ok(LayerResult { direction: Direction::Inbound, data: req })));
+        let chain = chain.with_context_pool(1).with_ordered();
+
+        let result = chain.handle_inbound_traced(Some(PayLoad::text("first"))).unwrap();
+        let payload = result.data.unwrap();
+        let trace_id = payload.ctx.trace_id().map(str::to_string);
+        assert!(trace_id.is_some());
+        assert_eq!(payload.ctx.data.get(SEQUENCE_KEY).map(String::as_str), Some("0"));
+        chain.recycle(payload);
+
+        // 复用的上下文已清空，不会带上前一个请求的追踪 id
+        let result = chain.handle_inbound_traced(Some(PayLoad::text("second"))).unwrap();
+        let payload = result.data.unwrap();
+        assert!(payload.ctx.trace_id().is_some());
+        assert_ne!(payload.ctx.trace_id().map(str::to_string), trace_id);
+        assert_eq!(payload.ctx.data.get(SEQUENCE_KEY).map(String::as_str), Some("1"));
+        assert_eq!(chain.context_pool_stats(), Some(ContextPoolStats { created: 1, reused: 1, idle: 0 }));
+    }
+
+    #[test]
+    fn test_pause_and_resume() {
+        let passthrough = || LayerBuilder::new()
+            .with_inbound_fn(|req| Ok(LayerResult { direction: Direction::Inbound, data: req }))
+            .with_outbound_fn(|req| Ok(LayerResult { direction: Direction::Outbound, data: req }))
+            .build().unwrap();
+        // 等待完整的一行再继续向上传递
+        let buffer = Mutex::new(String::new());
+        let framing = LayerBuilder::new()
+            .with_inbound_fn(move |req| {
+                let req = req.unwrap_or_default();
+                let mut buffer = buffer.lock().unwrap();
+                buffer.push_str(req.data.as_deref().unwrap_or_default());
+                if !buffer.ends_with('\n') {
+                    return Ok(LayerResult::pending(None));
+                }
+                let line = buffer.split_off(0);
+                Ok(LayerResult {
+                    direction: Direction::Inbound,
+                    data: Some(PayLoad::with_ctx(line.trim_end().to_string(), req.ctx)),
+                })
+            })
+            .with_outbound_fn(|req| Ok(LayerResult { direction: Direction::Outbound, data: req }))
+            .build().unwrap();
+        let upper = Layer::inbound_only(|req| {
+            let req = req.unwrap();
+            Ok(LayerResult {
+                direction: Direction::Inbound,
+                data: Some(PayLoad::with_ctx(req.data.unwrap().to_uppercase(), req.ctx)),
+            })
+        });
+
+        let mut chain = LayerChain::new();
+        chain.add_layer(passthrough());
+        chain.add_layer(framing);
+        chain.add_layer(upper);
+
+        let paused = chain.handle_inbound(Some(PayLoad::of("hel".to_string()))).unwrap();
+        assert!(paused.is_pending());
+        let token = paused.resume_token().unwrap();
+
+        let paused = chain.resume(token, Some(PayLoad::of("lo ".to_string()))).unwrap();
+        assert!(paused.is_pending());
+        let token = paused.resume_token().unwrap();
+
+        let done = chain.resume(token, Some(PayLoad::of("world\n".to_string()))).unwrap();
+        assert!(!done.is_pending());
+        assert_eq!(done.data.unwrap().data.unwrap(), "HELLO WORLD");
+    }
+
+    #[test]
+    fn test_ordered_chain_detects_reordering() {
+        // 出站时扣住第一个响应，先放行第二个
+        let held = Mutex::new(None::<PayLoad>);
+        let reorder = Layer::outbound_only(move |req| {
+            let req = req.unwrap();
+            let mut held = held.lock().unwrap();
+            if held.is_none() {
+                *held = Some(req);
+                return Ok(LayerResult::pending(None));
+            }
+            Ok(LayerResult { direction: Direction::Outbound, data: Some(req) })
+        });
+        let echo = Layer::inbound_only(|req| Ok(LayerResult { direction: Direction::Inbound, data: req }));
+
+        let mut chain = LayerChain::new().with_ordered();
+        chain.add_layer(reorder);
+        chain.add_layer(echo);
+
+        let first = chain.handle_inbound(Some(PayLoad::of("a".to_string()))).unwrap().data.unwrap();
+        let second = chain.handle_inbound(Some(PayLoad::of("b".to_string()))).unwrap().data.unwrap();
+        assert_eq!(first.ctx.data.get(SEQUENCE_KEY).map(String::as_str), Some("0"));
+        assert_eq!(second.ctx.data.get(SEQUENCE_KEY).map(String::as_str), Some("1"));
+
+        assert!(chain.handle_outbound(Some(first)).unwrap().is_pending());
+        let err = chain.handle_outbound(Some(second)).unwrap_err();
+        assert_eq!(err, LayerError::OutOfOrder { expected: 0, got: 1 });
+    }
+
+    #[test]
+    fn test_ordered_chain_accepts_in_order_responses() {
+        let echo = LayerBuilder::new()
+            .with_inbound_fn(|req| Ok(LayerResult { direction: Direction::Inbound, data: req }))
+            .with_outbound_fn(|req| Ok(LayerResult { direction: Direction::Outbound, data: req }))
+            .build().unwrap();
+        let mut chain = LayerChain::new().with_ordered();
+        chain.add_layer(echo);
+
+        let requests = (0..3)
+            .map(|i| chain.handle_inbound(Some(PayLoad::of(i.to_string()))).unwrap().data)
+            .collect::<Vec<_>>();
+        for request in requests {
+            assert!(chain.handle_outbound(request).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_inspect_chain() {
+        let framing = LayerBuilder::new()
+            .with_name("framing")
+            .with_codec("length-prefixed")
+            .with_inbound_fn(|req| Ok(LayerResult { direction: Direction::Inbound, data: req }))
+            .with_outbound_fn(|req| Ok(LayerResult { direction: Direction::Outbound, data: req }))
+            .build().unwrap();
+        let audit = LayerBuilder::new()
+            .with_name("audit")
+            .with_inbound_fn(|req| Ok(LayerResult { direction: Direction::Inbound, data: req }))
+            .with_outbound_passthrough()
+            .build().unwrap();
+        let noop = LayerBuilder::new()
+            .with_inbound_passthrough()
+            .with_outbound_passthrough()
+            .build().unwrap();
+        let compress = Layer::outbound_only(|req| Ok(LayerResult { direction: Direction::Outbound, data: req }));
+
+        let mut chain = LayerChain::new();
+        chain.add_layer(framing);
+        chain.add_layer(audit);
+        chain.add_layer(noop);
+        chain.add_layer(compress);
+
+        let inspection = |name: Option<&str>, inbound, outbound, codec: Option<&str>| LayerInspection {
+            name: name.map(str::to_string),
+            inbound,
+            outbound,
+            codec: codec.map(str::to_string),
+        };
+        assert_eq!(chain.inspect(), vec![
+            inspection(Some("framing"), true, true, Some("length-prefixed")),
+            inspection(Some("audit"), true, false, None),
+            inspection(None, false, false, None),
+            inspection(None, false, true, None),
+        ]);
+        assert_eq!(chain.describe()[2], "passthrough");
+    }
+
     #[test]
     fn test_layer_builder() {
        let layer0 = LayerBuilder::new().with_inbound_fn(|req|{
@@ -312,7 +2142,8 @@ mod tests {
               direction: Direction::Inbound,
               data: Some(PayLoad {
                   data: req.data,
-                  ctx:None,
+                  bytes: req.bytes,
+                  ctx: ChainContext::new(),
               }),
            })
        })
@@ -323,7 +2154,8 @@ mod tests {
               direction: Direction::Outbound,
               data: Some(PayLoad {
                   data: req.data,
-                  ctx: None,
+                  bytes: req.bytes,
+                  ctx: ChainContext::new(),
               }),
            })
        })
@@ -336,7 +2168,8 @@ mod tests {
               direction: Direction::Inbound,
               data: Some(PayLoad {
                   data: req.data,
-                  ctx: None,
+                  bytes: req.bytes,
+                  ctx: ChainContext::new(),
               }),
            })
        })
@@ -347,7 +2180,8 @@ mod tests {
             direction: Direction::Outbound, 
             data: Some(PayLoad {
                 data: req.data,
-                ctx: None,
+                bytes: req.bytes,
+                ctx: ChainContext::new(),
             })
          })
       })
@@ -360,13 +2194,15 @@ mod tests {
 
        let req = PayLoad {       
           data: Some("hello".to_string()),
-          ctx: None
+          bytes: None,
+          ctx: ChainContext::new()
         };
           
        chain.handle_inbound(Some(req)).unwrap();
        let req = PayLoad {       
             data: Some("hello".to_string()),
-            ctx: None
+            bytes: None,
+            ctx: ChainContext::new()
         };
        chain.handle_outbound(Some(req)).unwrap();
     }
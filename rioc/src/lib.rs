@@ -10,11 +10,32 @@ pub trait Callable<T> {
 
 pub mod function;
 pub mod layer;
+pub mod policy;
 pub mod task;
+#[cfg(feature = "tokio")]
+pub mod async_task;
 
 pub use function::Service;
 pub use function::service;
 pub use function::Function;
+pub use function::ServiceMut;
+pub use function::service_mut;
+pub use function::FunctionMut;
+pub use function::AsyncService;
+pub use function::async_service;
+pub use function::AsyncFunction;
+pub use function::ServiceExt;
+pub use function::Optional;
+pub use function::Map;
+pub use function::AndThen;
+pub use function::Batched;
+pub use function::BatchError;
+pub use function::CatchUnwind;
+pub use function::PanicInfo;
+pub use function::WithPolicy;
+pub use function::CircuitBreaker;
+pub use function::CircuitError;
+pub use function::CircuitState;
 pub use layer::LayerChain;
 pub use layer::Layer;
 pub use layer::LayerResult;
@@ -24,12 +45,35 @@ pub use layer::PayLoad;
 pub use layer::SharedLayer;
 pub use layer::ProtocolAware;
 pub use layer::LayerBuilder;
+pub use layer::ConnectedChains;
+pub use layer::ChainService;
+pub use layer::LayerKind;
+pub use layer::LayerInspection;
+pub use layer::ChainState;
+pub use layer::LayerChainTestHarness;
+pub use layer::ContextPoolStats;
+pub use layer::LayerError;
+pub use layer::TRACE_ID_KEY;
+pub use layer::SEQUENCE_KEY;
+pub use layer::ResumeToken;
+pub use policy::Policy;
+pub use policy::PolicyError;
 pub use task::JobTask;
+pub use task::JobTaskBuilder;
+pub use task::TaskRegistry;
 pub use task::TaskEvent;
+pub use task::TaskSender;
+pub use task::Progress;
+pub use task::TaskOutcome;
+pub use task::JobContext;
+pub use task::CancelReason;
+pub use task::TaskIter;
+#[cfg(feature = "tokio")]
+pub use async_task::AsyncJobTask;
 
 pub use imacro::{
-    inject, injectable, module, provider, InjectableHelperAttr, ModuleHelperAttr,
-    ProviderHelperAttr, ScopeHelperAttr,
+    inject, injectable, module, provider, Injectable, InjectableHelperAttr, ModuleHelperAttr,
+    ProtocolLayer, ProviderHelperAttr, ScopeHelperAttr,
 };
 
 
@@ -124,6 +168,137 @@ pub trait Injectable<'prov, Injecty, Provider> {
 /// ```
 pub trait Import<Module> {
     fn reference(&self) -> &Module;
+
+    /// Resolve a type exported by the imported module, without requiring `Self` to be a provider.
+    /// Exports are checked at compile time, so a type the module does not export fails to build
+    /// rather than returning `None`.
+    /// ```rust
+    /// use rioc::{injectable, module, provider, Import};
+    ///
+    /// #[injectable]
+    /// struct Hidden(#[inject(123)] i32);
+    ///
+    /// #[injectable]
+    /// #[module]
+    /// struct Module {
+    ///     #[export]
+    ///     hidden: Hidden,
+    /// }
+    ///
+    /// struct Host(Module);
+    ///
+    /// impl Import<Module> for Host {
+    ///     fn reference(&self) -> &Module {
+    ///         &self.0
+    ///     }
+    /// }
+    ///
+    /// #[provider]
+    /// struct InitProvider;
+    ///
+    /// let host = Host(InitProvider.provide());
+    /// let hidden: Option<&Hidden> = host.import_resolve();
+    /// assert_eq!(hidden.map(|hidden| hidden.0), Some(123));
+    /// ```
+    fn import_resolve<'prov, T>(&'prov self) -> Option<T>
+    where
+        Self: Sized,
+        T: Injectable<'prov, T, Self>,
+    {
+        Some(T::inject(self))
+    }
+}
+
+/// List, at runtime, the types a provider declares. Implemented by the `provider` macro.
+/// ```rust
+/// use rioc::{provider, ProviderManifest};
+///
+/// #[provider]
+/// #[provide(i32, 42)]
+/// struct Provider {
+///     #[provide]
+///     name: String,
+/// }
+///
+/// assert_eq!(Provider::provides(), vec!["i32", "&String"]);
+/// assert!(Provider::imports().is_empty());
+/// ```
+pub trait ProviderManifest {
+    /// Names of the types declared with `#[provide]`.
+    fn provides() -> Vec<&'static str>;
+    /// Names of the modules declared with `#[import]`.
+    fn imports() -> Vec<&'static str>;
+}
+
+/// A dependency built from the provider on first access instead of at injection time.
+/// ```rust
+/// use rioc::{injectable, provider, Lazy};
+///
+/// #[injectable]
+/// struct Expensive(#[inject(42)] i32);
+///
+/// #[injectable]
+/// struct Facade<'a> {
+///     dep: Lazy<'a, Expensive>,
+/// }
+///
+/// #[provider]
+/// struct Provider;
+///
+/// let facade: Facade = Provider.provide();
+/// assert_eq!(facade.dep.get().0, 42);
+/// ```
+pub struct Lazy<'a, T> {
+    init: std::cell::Cell<Option<Box<dyn FnOnce() -> T + 'a>>>,
+    value: once_cell::unsync::OnceCell<T>,
+}
+
+impl<'a, T> Lazy<'a, T> {
+    pub fn new(init: impl FnOnce() -> T + 'a) -> Self {
+        Self {
+            init: std::cell::Cell::new(Some(Box::new(init))),
+            value: once_cell::unsync::OnceCell::new(),
+        }
+    }
+
+    /// Build the value on the first call and return it.
+    pub fn get(&self) -> &T {
+        self.value.get_or_init(|| match self.init.take() {
+            Some(init) => init(),
+            None => panic!("Lazy value initialization panicked earlier"),
+        })
+    }
+
+    /// Whether the value has been built yet.
+    pub fn is_initialized(&self) -> bool {
+        self.value.get().is_some()
+    }
+}
+
+impl<T> std::ops::Deref for Lazy<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        self.get()
+    }
+}
+
+impl<T: std::fmt::Debug> std::fmt::Debug for Lazy<'_, T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.value.get() {
+            Some(value) => f.debug_tuple("Lazy").field(value).finish(),
+            None => f.write_str("Lazy(<uninit>)"),
+        }
+    }
+}
+
+impl<'prov: 'a, 'a, T: 'a, P> Injectable<'prov, Lazy<'a, T>, P> for Lazy<'a, T>
+where
+    P: Provider<'prov, T>,
+{
+    fn inject(provider: &'prov P) -> Lazy<'a, T> {
+        Lazy::new(move || provider.provide())
+    }
 }
 
 /// For internal purposes only. Should not be used.
@@ -131,6 +306,48 @@ pub trait RefInjectable<'prov, Value, Provider> {
     fn inject(&'prov self, provider: &'prov Provider) -> Value;
 }
 
+/// Check at compile time that a provider can provide every listed type,
+/// including everything the listed injectables need.
+/// ```rust
+/// use rioc::{assert_provides, injectable, provider};
+///
+/// #[injectable]
+/// struct Facade(i32);
+///
+/// #[injectable]
+/// struct RefFacade<'a>(&'a String);
+///
+/// #[provider]
+/// #[provide(i32, 42)]
+/// struct Provider {
+///     #[provide]
+///     name: String,
+/// }
+///
+/// assert_provides!(Provider => [Facade, RefFacade<'static>, i32]);
+/// ```
+#[macro_export]
+macro_rules! assert_provides {
+    ($provider:ty => [$($target:ty),* $(,)?]) => {
+        const _: fn() = || {
+            fn assert_provides<'prov, P: ?Sized $(+ $crate::Provides<'prov, $target>)*>() {}
+            assert_provides::<$provider>();
+        };
+    };
+}
+
+/// For internal purposes only. Names the check made by [`assert_provides!`] so a missing
+/// dependency is reported against the provider and the listed type, not the leaf it failed on.
+#[doc(hidden)]
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` cannot provide `{T}`",
+    label = "`{T}` or one of its dependencies is not provided by `{Self}`"
+)]
+pub trait Provides<'prov, T> {}
+
+#[diagnostic::do_not_recommend]
+impl<'prov, T, P: ?Sized + Provider<'prov, T>> Provides<'prov, T> for P {}
+
 
 
 
@@ -0,0 +1,265 @@
+use core::fmt;
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::mpsc::RecvTimeoutError;
+use std::sync::Arc;
+use std::time::Duration;
+
+use may::coroutine;
+use may::sync::mpsc;
+
+/// Retry and timeout settings shared by [`ServiceExt::with_policy`](crate::ServiceExt::with_policy)
+/// and [`JobTaskBuilder::with_policy`](crate::JobTaskBuilder::with_policy).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Policy {
+    /// Total number of attempts, including the first one. Zero is treated as one.
+    pub max_attempts: usize,
+    /// Upper bound for a single attempt; `None` waits forever.
+    pub timeout: Option<Duration>,
+    /// Delay before the first retry, doubled for every retry after it.
+    pub backoff: Duration,
+    /// Maximum random delay added on top of each backoff.
+    pub jitter: Duration,
+}
+
+impl Default for Policy {
+    fn default() -> Self {
+        Policy {
+            max_attempts: 1,
+            timeout: None,
+            backoff: Duration::ZERO,
+            jitter: Duration::ZERO,
+        }
+    }
+}
+
+impl Policy {
+    pub fn new(max_attempts: usize) -> Self {
+        Policy { max_attempts, ..Policy::default() }
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    pub fn with_backoff(mut self, backoff: Duration) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    pub fn with_jitter(mut self, jitter: Duration) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    /// The delay slept before retry number `retry` (starting at 1), jitter included.
+    pub fn delay(&self, retry: u32) -> Duration {
+        let factor = 1u32.checked_shl(retry.saturating_sub(1)).unwrap_or(u32::MAX);
+        let backoff = self.backoff.checked_mul(factor).unwrap_or(Duration::MAX);
+        backoff.saturating_add(self.random_jitter())
+    }
+
+    fn random_jitter(&self) -> Duration {
+        let nanos = self.jitter.as_nanos().min(u64::MAX as u128) as u64;
+        if nanos == 0 {
+            return Duration::ZERO;
+        }
+        let mut hasher = RandomState::new().build_hasher();
+        hasher.write_u64(nanos);
+        Duration::from_nanos(hasher.finish() % (nanos + 1))
+    }
+
+    /// Runs `attempt` until it succeeds or the attempts are used up, sleeping between tries.
+    /// With a timeout each attempt runs on its own coroutine, which is cancelled once the deadline passes.
+    pub(crate) fn run<T, E, F>(&self, attempt: F) -> Result<T, PolicyError<E>>
+    where
+        F: Fn() -> Result<T, E> + Send + Sync + 'static,
+        T: Send + 'static,
+        E: Send + 'static,
+    {
+        let attempt = Arc::new(attempt);
+        let max_attempts = self.max_attempts.max(1);
+        let mut attempts = 0;
+        loop {
+            if attempts > 0 {
+                coroutine::sleep(self.delay(attempts as u32));
+            }
+            attempts += 1;
+            let outcome = match self.timeout {
+                Some(timeout) => run_with_timeout(attempt.clone(), timeout),
+                None => Some(attempt()),
+            };
+            match outcome {
+                Some(Ok(value)) => return Ok(value),
+                Some(Err(error)) if attempts >= max_attempts => {
+                    return Err(PolicyError::Failed { attempts, error })
+                }
+                None if attempts >= max_attempts => return Err(PolicyError::TimedOut { attempts }),
+                _ => {}
+            }
+        }
+    }
+}
+
+// 超时返回 None；尝试中的 panic 会传递给调用方
+fn run_with_timeout<T, E, F>(attempt: Arc<F>, timeout: Duration) -> Option<Result<T, E>>
+where
+    F: Fn() -> Result<T, E> + Send + Sync + 'static,
+    T: Send + 'static,
+    E: Send + 'static,
+{
+    let (tx, rx) = mpsc::channel();
+    let handle = unsafe {
+        coroutine::spawn(move || {
+            let _ = tx.send(attempt());
+        })
+    };
+    match rx.recv_timeout(timeout) {
+        Ok(outcome) => Some(outcome),
+        Err(RecvTimeoutError::Timeout) => {
+            unsafe { handle.coroutine().cancel() };
+            None
+        }
+        Err(RecvTimeoutError::Disconnected) => match handle.join() {
+            Err(payload) => std::panic::resume_unwind(payload),
+            Ok(()) => None,
+        },
+    }
+}
+
+/// The error returned once a [`Policy`] gives up.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PolicyError<E> {
+    /// The last attempt failed with `error`.
+    Failed { attempts: usize, error: E },
+    /// The last attempt did not finish within the per-try timeout.
+    TimedOut { attempts: usize },
+}
+
+impl<E: fmt::Display> fmt::Display for PolicyError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PolicyError::Failed { attempts, error } => {
+                write!(f, "failed after {} attempts: {}", attempts, error)
+            }
+            PolicyError::TimedOut { attempts } => {
+                write!(f, "timed out after {} attempts", attempts)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Mutex;
+    use std::time::Instant;
+
+    use serde_json::json;
+
+    use super::*;
+    use crate::function::{service, Service, ServiceExt};
+    use crate::task::{JobTask, TaskEvent};
+
+    type Calls = Arc<Mutex<Vec<Instant>>>;
+
+    // 前 failures 次调用失败，并记录每次调用的时间
+    fn flaky(failures: usize) -> (impl Fn() -> Result<usize, String> + Send + Sync + 'static, Calls) {
+        let calls = Arc::new(Mutex::new(Vec::new()));
+        let seen = calls.clone();
+        let attempt = move || {
+            let mut calls = seen.lock().unwrap();
+            calls.push(Instant::now());
+            if calls.len() <= failures {
+                Err(format!("attempt {} failed", calls.len()))
+            } else {
+                Ok(calls.len())
+            }
+        };
+        (attempt, calls)
+    }
+
+    fn gaps(calls: &Mutex<Vec<Instant>>) -> Vec<Duration> {
+        let calls = calls.lock().unwrap();
+        calls.windows(2).map(|w| w[1] - w[0]).collect()
+    }
+
+    fn run_task(policy: Policy, failures: usize) -> (Vec<TaskEvent<usize, String>>, Calls) {
+        let (attempt, calls) = flaky(failures);
+        let job: JobTask<usize, String, ()> = JobTask::builder(json!({}))
+            .with_policy(policy)
+            .spawn_fallible(move |_, sender, _, _| {
+                let n = attempt()?;
+                let _ = sender.send(TaskEvent::Data(n));
+                Ok(())
+            });
+        let events = job.into_iter().collect();
+        (events, calls)
+    }
+
+    #[test]
+    fn test_delay_doubles() {
+        let policy = Policy::new(4).with_backoff(Duration::from_millis(10));
+        assert_eq!(policy.delay(1), Duration::from_millis(10));
+        assert_eq!(policy.delay(2), Duration::from_millis(20));
+        assert_eq!(policy.delay(3), Duration::from_millis(40));
+
+        let policy = policy.with_jitter(Duration::from_millis(5));
+        let delay = policy.delay(1);
+        assert!(delay >= Duration::from_millis(10) && delay <= Duration::from_millis(15));
+    }
+
+    #[test]
+    fn test_same_policy_for_service_and_task() {
+        let policy = Policy::new(3).with_backoff(Duration::from_millis(20));
+
+        let (attempt, service_calls) = flaky(2);
+        let svc = service(move |_: ()| attempt()).with_policy(policy.clone());
+        assert_eq!(svc.call(()), Ok(3));
+
+        let (events, task_calls) = run_task(policy.clone(), 2);
+        assert!(matches!(events.as_slice(), [TaskEvent::Data(3), TaskEvent::Done]));
+
+        for calls in [&service_calls, &task_calls] {
+            let gaps = gaps(calls);
+            assert_eq!(gaps.len(), 2);
+            assert!(gaps[0] >= Duration::from_millis(20));
+            assert!(gaps[1] >= Duration::from_millis(40));
+        }
+    }
+
+    #[test]
+    fn test_same_policy_gives_up_for_service_and_task() {
+        let policy = Policy::new(2);
+
+        let (attempt, service_calls) = flaky(usize::MAX);
+        let svc = service(move |_: ()| attempt()).with_policy(policy.clone());
+        assert_eq!(
+            svc.call(()),
+            Err(PolicyError::Failed { attempts: 2, error: "attempt 2 failed".to_string() })
+        );
+
+        let (events, task_calls) = run_task(policy, usize::MAX);
+        assert!(matches!(events.as_slice(), [TaskEvent::SetupFailed(e), TaskEvent::Done] if e == "attempt 2 failed"));
+
+        assert_eq!(service_calls.lock().unwrap().len(), 2);
+        assert_eq!(task_calls.lock().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_per_try_timeout() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let seen = calls.clone();
+        let svc = service(move |_: ()| -> Result<(), String> {
+            seen.fetch_add(1, Ordering::SeqCst);
+            coroutine::sleep(Duration::from_secs(5));
+            Ok(())
+        }).with_policy(Policy::new(2).with_timeout(Duration::from_millis(20)));
+
+        let started = Instant::now();
+        assert_eq!(svc.call(()), Err(PolicyError::TimedOut { attempts: 2 }));
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+}
@@ -1,111 +1,696 @@
 #![allow(dead_code)]
 
-use crossbeam::channel::{self, after, Receiver, Sender};
+use crossbeam::channel::{self, after, Receiver, SendError, Sender, TrySendError};
 use may::coroutine::{self, JoinHandle};
+use std::marker::PhantomData;
 use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::Arc;
+use std::sync::{Arc, Mutex, Weak};
 use std::time::Duration;
 use crossbeam::select;
 use serde_json::Value;
+use once_cell::sync::Lazy;
+
+use crate::function::{panic_message, Service};
+use crate::policy::{Policy, PolicyError};
 
 #[derive(Debug)]
 pub enum TaskEvent<T, E> {
     Data(T),         // 任务发送的数据项
-    Progress((u8,u32)),    // 任务进度更新
+    Progress(Progress),    // 任务进度更新
     Done,            // 任务正常完成
-    Cancelled,       // 任务被取消
+    Cancelled(CancelReason), // 任务被取消，并说明原因
     Error(E),        // 任务返回错误
+    SetupFailed(E),  // 任务在发出任何数据或进度之前就返回了错误
     Panic(String),   // 任务 panic
 }
 
+// JobTask::join 返回的任务结果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TaskOutcome<T, E> {
+    Completed(Vec<T>),       // 任务正常完成，带有收到的所有数据项
+    Cancelled(CancelReason), // 任务被取消
+    Failed(E),               // 任务返回错误
+    Panicked(String),        // 任务 panic
+}
+
+// 任务进度；percent 总是在 0..=100 之内
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Progress {
+    pub percent: u8,
+    pub current: u32,
+    pub total: u32,
+}
+
+impl Progress {
+    // 按 current/total 计算百分比，current 超过 total 时按 100% 计算；total 为 0 时为 0%
+    pub fn new(current: u32, total: u32) -> Self {
+        let percent = match total {
+            0 => 0,
+            total => (u64::from(current) * 100 / u64::from(total)).min(100) as u8,
+        };
+        Progress { percent, current, total }
+    }
+
+    // 只有百分比的进度，超过 100 的值按 100 处理
+    pub fn from_percent(percent: u8) -> Self {
+        let percent = percent.min(100);
+        Progress { percent, current: u32::from(percent), total: 100 }
+    }
+}
+
+// 任务被取消的原因
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CancelReason {
+    Requested, // 调用者主动取消
+    Timeout,   // 超过了 cancel_after 设置的时间
+    Parent,    // 上级任务被取消
+    Pruned,    // 缓冲的事件过多，被 TaskRegistry::prune 取消
+}
+
+// 事件通道已满时，TaskSender::send 两次重试之间让出协程的时间
+const SEND_BACKOFF: Duration = Duration::from_millis(1);
+
+// 任务闭包用来发送事件的 Sender
+// 在发出任何 Data 或 Progress 之前发送的 Error 会以 SetupFailed 发出，所有克隆共享同一个状态
+pub struct TaskSender<T, E> {
+    inner: Sender<TaskEvent<T, E>>,
+    started: Arc<AtomicBool>, // 是否已经发出过 Data 或 Progress
+}
+
+impl<T, E> Clone for TaskSender<T, E> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            started: self.started.clone(),
+        }
+    }
+}
+
+impl<T, E> TaskSender<T, E> {
+    fn new(inner: Sender<TaskEvent<T, E>>) -> Self {
+        Self { inner, started: Arc::new(AtomicBool::new(false)) }
+    }
+
+    // 发送事件；通道有容量限制且已满时等待消费者取走事件。
+    // 等待时以 SEND_BACKOFF 为间隔让出协程，而不是阻塞 may 的工作线程，等待中的任务也可以被 cancel 打断；
+    // 容量为 0 的通道必须和接收方直接交接，仍然阻塞等待
+    pub fn send(&self, event: TaskEvent<T, E>) -> Result<(), SendError<TaskEvent<T, E>>> {
+        let mut event = self.classify(event);
+        if self.inner.capacity() == Some(0) {
+            return self.inner.send(event);
+        }
+        loop {
+            match self.inner.try_send(event) {
+                Ok(()) => return Ok(()),
+                Err(TrySendError::Full(rejected)) => {
+                    event = rejected;
+                    coroutine::sleep(SEND_BACKOFF);
+                }
+                Err(TrySendError::Disconnected(rejected)) => return Err(SendError(rejected)),
+            }
+        }
+    }
+
+    // 通道已满或已关闭时立即返回错误
+    pub fn try_send(&self, event: TaskEvent<T, E>) -> Result<(), TrySendError<TaskEvent<T, E>>> {
+        self.inner.try_send(self.classify(event))
+    }
+
+    // 在任务开始工作之前出现的错误视为启动失败
+    fn classify(&self, event: TaskEvent<T, E>) -> TaskEvent<T, E> {
+        match event {
+            TaskEvent::Data(_) | TaskEvent::Progress(_) => {
+                self.started.store(true, Ordering::Release);
+                event
+            }
+            TaskEvent::Error(e) if !self.started.load(Ordering::Acquire) => TaskEvent::SetupFailed(e),
+            event => event,
+        }
+    }
+}
+
+// 任务侧的上下文，用于保存检查点
 #[derive(Clone)]
-pub struct JobTask<T: Send + 'static, E: Send + 'static,D: Send + 'static>  {
+pub struct JobContext {
+    checkpoint: Arc<Mutex<Option<Value>>>,
+}
+
+impl JobContext {
+    // 保存最新的检查点，覆盖之前的值
+    pub fn checkpoint(&self, state: Value) {
+        *self.checkpoint.lock().unwrap() = Some(state);
+    }
+
+    // 最近一次的检查点；恢复的任务在启动时会拿到上一个任务的检查点
+    pub fn last_checkpoint(&self) -> Option<Value> {
+        self.checkpoint.lock().unwrap().clone()
+    }
+}
+
+#[derive(Clone)]
+pub struct JobTask<T: Send + 'static, E: Send + 'static,D: Send + 'static, C: Send + 'static = ()>  {
     is_cancelled: Arc<AtomicBool>,
     handle: Option<Arc<JoinHandle<()>>>,
-    event_rx:  Receiver<TaskEvent<T, E>>,
+    event_rx:  Arc<Receiver<TaskEvent<T, E>>>, // 注册表只持有 Weak，句柄全部释放后通道随之断开
     _event_tx: Sender<TaskEvent<T, E>>, // 保持 channel 开启
     sender: Sender<D>, // 用于向任务发送数据
+    control: Sender<C>, // 用于向任务发送控制消息
+    checkpoint: Arc<Mutex<Option<Value>>>, // 任务保存的最新检查点
+    progress: Arc<Mutex<Option<Progress>>>, // 目前为止最大的进度
+    cancel_reason: Arc<Mutex<Option<CancelReason>>>, // 第一次取消时记录的原因
 }
 
 
 impl<T: Send + 'static, E: Send + 'static, D: Send + 'static> JobTask<T, E, D>  {
     pub fn new<F>(params: Value,task: F) -> Self  
     where
-        F: FnOnce(Value,Sender<TaskEvent<T, E>>, Receiver<D>) + Send + 'static,
+        F: FnOnce(Value,TaskSender<T, E>, Receiver<D>) + Send + 'static,
+    {
+        Self::spawn(params, None, Arc::new(Mutex::new(None)), move |params, sender, receiver, _| {
+            task(params, sender, receiver)
+        })
+    }
+
+    // 事件通道最多缓冲 capacity 个事件：消费者跟不上时，任务中的 sender.send 会等待，直到事件被取走。
+    // 等待时让出协程，不占住 may 的工作线程，其他协程照常运行；等待中的任务可以被 cancel 打断。
+    // capacity 为 0 时 send 必须和接收方直接交接，会阻塞所在的工作线程
+    pub fn with_capacity<F>(params: Value, capacity: usize, task: F) -> Self
+    where
+        F: FnOnce(Value,TaskSender<T, E>, Receiver<D>) + Send + 'static,
+    {
+        Self::spawn(params, Some(capacity), Arc::new(Mutex::new(None)), move |params, sender, receiver, _| {
+            task(params, sender, receiver)
+        })
+    }
+
+    pub fn builder(params: Value) -> JobTaskBuilder<T, E, D> {
+        JobTaskBuilder {
+            params,
+            policy: Policy::default(),
+            registry: None,
+            _types: PhantomData,
+        }
+    }
+
+    // 可恢复的任务：闭包通过 JobContext 保存检查点，并从传入的检查点继续
+    pub fn new_resumable<F>(params: Value, checkpoint: Option<Value>, task: F) -> Self
+    where
+        F: FnOnce(Value, JobContext, TaskSender<T, E>, Receiver<D>) + Send + 'static,
+    {
+        let checkpoint = Arc::new(Mutex::new(checkpoint));
+        let ctx = JobContext { checkpoint: checkpoint.clone() };
+        Self::spawn(params, None, checkpoint, move |params, sender, receiver, _| {
+            task(params, ctx, sender, receiver)
+        })
+    }
+
+    // 超过 deadline 后以 CancelReason::Timeout 取消任务，和 cancel_after 相同；
+    // 闭包收到取消标志，可以在不让出协程的循环中检查它并自行返回
+    pub fn new_with_deadline<F>(params: Value, deadline: Duration, task: F) -> Self
+    where
+        F: FnOnce(Value, Arc<AtomicBool>, TaskSender<T, E>, Receiver<D>) + Send + 'static,
     {
         let is_cancelled = Arc::new(AtomicBool::new(false));
-        let (event_tx, event_rx) = channel::unbounded();
+        let flag = is_cancelled.clone();
+        let job = Self::spawn_with_flag(params, None, Arc::new(Mutex::new(None)), is_cancelled, move |params, sender, receiver, _| {
+            task(params, flag, sender, receiver)
+        });
+        job.cancel_after(deadline);
+        job
+    }
+}
+
+impl<T: Send + 'static, E: Send + 'static, D: Send + 'static, C: Send + 'static> JobTask<T, E, D, C>  {
+    // capacity 为 None 时事件通道不限长度
+    fn spawn<F>(params: Value, capacity: Option<usize>, checkpoint: Arc<Mutex<Option<Value>>>, task: F) -> Self
+    where
+        F: FnOnce(Value,TaskSender<T, E>, Receiver<D>, Receiver<C>) + Send + 'static,
+    {
+        Self::spawn_with_flag(params, capacity, checkpoint, Arc::new(AtomicBool::new(false)), task)
+    }
+
+    // 使用调用方提供的取消标志，任务闭包可以持有同一个标志
+    fn spawn_with_flag<F>(
+        params: Value,
+        capacity: Option<usize>,
+        checkpoint: Arc<Mutex<Option<Value>>>,
+        is_cancelled: Arc<AtomicBool>,
+        task: F,
+    ) -> Self
+    where
+        F: FnOnce(Value,TaskSender<T, E>, Receiver<D>, Receiver<C>) + Send + 'static,
+    {
+        let (event_tx, event_rx) = match capacity {
+            Some(capacity) => channel::bounded(capacity),
+            None => channel::unbounded(),
+        };
         let (data_tx, data_rx) = channel::unbounded();
+        let (control_tx, control_rx) = channel::unbounded();
 
 
         let flag = is_cancelled.clone();
-        let sender = event_tx.clone();
+        let sender = TaskSender::new(event_tx.clone());
+        // 终止事件直接发送：任务被取消后协程不能再让出，否则会再次触发取消
+        let events = event_tx.clone();
+        let cancel_reason = Arc::new(Mutex::new(None));
+        let reason = cancel_reason.clone();
+        let cancelled = move || {
+            let reason = reason.lock().unwrap().clone();
+            TaskEvent::Cancelled(reason.unwrap_or(CancelReason::Requested))
+        };
 
         // 在协程中运行任务
         let handle = unsafe { coroutine::spawn(move || {
             // 检查是否已被取消
             if flag.load(Ordering::Acquire) {
-                let _ = sender.send(TaskEvent::Cancelled);
+                let _ = events.send(cancelled());
                 return;
             }
 
             // 执行任务并捕获 panic
             let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
-                task(params,sender.clone(),data_rx.clone());
+                task(params,sender.clone(),data_rx.clone(),control_rx.clone());
             }));
 
             match result {
+                Ok(_) if flag.load(Ordering::Acquire) => {
+                    // 任务看到取消标志后自行返回
+                    let _ = events.send(cancelled());
+                }
                 Ok(_) => {
                     // 任务正常完成
-                    let _ = sender.send(TaskEvent::Done);
+                    let _ = events.send(TaskEvent::Done);
+                }
+                Err(_) if flag.load(Ordering::Acquire) => {
+                    // 协程被取消时会在内部 panic
+                    let _ = events.send(cancelled());
                 }
-                Err(_) => {
-                    // 任务 panic
-                    let _ = sender.send(TaskEvent::Panic(format!("panic")));
+                Err(payload) => {
+                    // 任务 panic，带上 panic 的消息
+                    let _ = events.send(TaskEvent::Panic(panic_message(payload)));
                 }
             }
         }) };
 
-        JobTask {
+        let job = JobTask {
             is_cancelled: is_cancelled,
             handle: Some(Arc::new(handle)),
-            event_rx: event_rx,
+            event_rx: Arc::new(event_rx),
             _event_tx: event_tx,
             sender: data_tx,
-        }
+            control: control_tx,
+            checkpoint,
+            progress: Arc::new(Mutex::new(None)),
+            cancel_reason,
+        };
+        // 所有任务在创建时登记到全局注册表
+        TaskRegistry::global().track(&job);
+        job
+    }
+
+    pub fn last_checkpoint(&self) -> Option<Value> {
+        self.checkpoint.lock().unwrap().clone()
     }
 
     // 中断任务
     pub fn cancel(&mut self) {
-        self.is_cancelled.store(true, Ordering::Relaxed);
+        self.cancel_with(CancelReason::Requested);
+    }
+
+    // 带原因中断任务；只有第一次取消的原因会被记录
+    pub fn cancel_with(&mut self, reason: CancelReason) {
+        Self::record_cancel(&self.cancel_reason, &self.is_cancelled, reason);
         if let Some(handle) = self.handle.take() {
             // 强制取消协程（如果标志位未被及时检查）
             unsafe { handle.coroutine().cancel() };
         }
     }
 
+    // 超时后以 CancelReason::Timeout 取消任务
+    pub fn cancel_after(&self, timeout: Duration) {
+        let Some(handle) = self.handle.clone() else {
+            return;
+        };
+        let reason = self.cancel_reason.clone();
+        let flag = self.is_cancelled.clone();
+        std::thread::spawn(move || {
+            std::thread::sleep(timeout);
+            if handle.is_done() || flag.load(Ordering::Acquire) {
+                return;
+            }
+            Self::record_cancel(&reason, &flag, CancelReason::Timeout);
+            unsafe { handle.coroutine().cancel() };
+        });
+    }
+
+    // 取消的原因；任务没有被取消时返回 None
+    pub fn cancel_reason(&self) -> Option<CancelReason> {
+        self.cancel_reason.lock().unwrap().clone()
+    }
+
+    fn record_cancel(slot: &Mutex<Option<CancelReason>>, flag: &AtomicBool, reason: CancelReason) {
+        slot.lock().unwrap().get_or_insert(reason);
+        flag.store(true, Ordering::Release);
+    }
+
+    // 任务是否已被取消
+    pub fn is_cancelled(&self) -> bool {
+        self.is_cancelled.load(Ordering::Acquire)
+    }
+
     pub fn try_recv(&self) -> Option<TaskEvent<T, E>> {
-        self.event_rx.try_recv().ok()
+        self.event_rx.try_recv().ok().map(|event| self.observe(event))
     }
 
     pub fn recv(&self) -> Option<TaskEvent<T, E>> {
-        self.event_rx.recv().ok()
+        self.event_rx.recv().ok().map(|event| self.observe(event))
     }
 
     pub fn recv_timeout(&self, timeout: Duration) -> Option<TaskEvent<T, E>> {
-        self.event_rx.recv_timeout(timeout).ok()
+        self.event_rx.recv_timeout(timeout).ok().map(|event| self.observe(event))
+    }
+
+    // 阻塞直到任务结束，收集途中的 Data；返回过错误的任务结果为 Failed
+    pub fn join(self) -> TaskOutcome<T, E> {
+        let mut data = Vec::new();
+        let mut error = None;
+        for event in self {
+            match event {
+                TaskEvent::Data(item) => data.push(item),
+                TaskEvent::Progress(_) => {}
+                TaskEvent::Error(e) | TaskEvent::SetupFailed(e) => error = Some(e),
+                TaskEvent::Done => {
+                    return match error {
+                        Some(e) => TaskOutcome::Failed(e),
+                        None => TaskOutcome::Completed(data),
+                    }
+                }
+                TaskEvent::Cancelled(reason) => return TaskOutcome::Cancelled(reason),
+                TaskEvent::Panic(message) => return TaskOutcome::Panicked(message),
+            }
+        }
+        TaskOutcome::Cancelled(CancelReason::Requested)
+    }
+
+    // 最近收到的进度，供后加入的消费者查询；进度只增不减，回退的进度不会覆盖它
+    // 只统计通过 recv 系列方法取走的事件
+    pub fn last_progress(&self) -> Option<Progress> {
+        *self.progress.lock().unwrap()
+    }
+
+    // 记录收到的最大进度
+    fn observe(&self, event: TaskEvent<T, E>) -> TaskEvent<T, E> {
+        match event {
+            TaskEvent::Progress(mut progress) => {
+                progress.percent = progress.percent.min(100);
+                let mut last = self.progress.lock().unwrap();
+                if last.map_or(true, |last| progress.percent >= last.percent) {
+                    *last = Some(progress);
+                }
+                TaskEvent::Progress(progress)
+            }
+            event => event,
+        }
     }
 
     pub fn send(&self, data: D) {
         let _ = self.sender.send(data);
     }
+
+    // 通过控制通道发送消息，和数据通道互不影响
+    pub fn send_control(&self, msg: C) {
+        let _ = self.control.send(msg);
+    }
+
+    // 把任务最近一次的 Data 暴露为 Service：每次 call 先取走已到达的事件，再返回最新的数据
+    // 该 Service 会消费事件通道，不要和 recv 系列方法同时使用
+    pub fn latest_service(&self) -> impl Service<(), Option<T>> + Clone
+    where
+        T: Clone,
+    {
+        LatestData {
+            event_rx: Receiver::clone(&self.event_rx),
+            latest: Arc::new(Mutex::new(None)),
+        }
+    }
+}
+
+// 按需配置额外通道的任务构建器
+pub struct JobTaskBuilder<T, E, D, C = ()> {
+    params: Value,
+    policy: Policy, // spawn_fallible 使用的重试策略
+    registry: Option<TaskRegistry>, // 创建后登记到该注册表
+    _types: PhantomData<(T, E, D, C)>,
+}
+
+impl<T: Send + 'static, E: Send + 'static, D: Send + 'static> JobTaskBuilder<T, E, D> {
+    // 增加一个类型为 C 的控制通道，任务闭包额外收到它的 Receiver
+    pub fn with_control_channel<C: Send + 'static>(self) -> JobTaskBuilder<T, E, D, C> {
+        JobTaskBuilder {
+            params: self.params,
+            policy: self.policy,
+            registry: self.registry,
+            _types: PhantomData,
+        }
+    }
+}
+
+impl<T: Send + 'static, E: Send + 'static, D: Send + 'static, C: Send + 'static> JobTaskBuilder<T, E, D, C> {
+    // 启动任务；没有配置控制通道时 C 为 ()，对应的 Receiver 不会收到消息
+    pub fn spawn<F>(self, task: F) -> JobTask<T, E, D, C>
+    where
+        F: FnOnce(Value, TaskSender<T, E>, Receiver<D>, Receiver<C>) + Send + 'static,
+    {
+        let job = JobTask::spawn(self.params, None, Arc::new(Mutex::new(None)), task);
+        if let Some(registry) = &self.registry {
+            registry.track(&job);
+        }
+        job
+    }
+
+    // 创建的任务额外登记到 registry，用于统计和限制一组任务；所有任务总是登记在 TaskRegistry::global()
+    pub fn with_registry(mut self, registry: &TaskRegistry) -> Self {
+        self.registry = Some(registry.clone());
+        self
+    }
+
+    // 设置 spawn_fallible 的重试和超时策略，默认只尝试一次
+    pub fn with_policy(mut self, policy: Policy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    // 启动可重试的任务：闭包返回 Err 时按策略重新执行，用尽次数后发送最后一次的 Error
+    // 最后一次尝试超时则发送 Cancelled(CancelReason::Timeout)
+    pub fn spawn_fallible<F>(self, task: F) -> JobTask<T, E, D, C>
+    where
+        F: Fn(Value, TaskSender<T, E>, Receiver<D>, Receiver<C>) -> Result<(), E> + Send + Sync + 'static,
+    {
+        let policy = self.policy.clone();
+        self.spawn(move |params, sender, receiver, control| {
+            let events = sender.clone();
+            let outcome = policy.run(move || {
+                task(params.clone(), sender.clone(), receiver.clone(), control.clone())
+            });
+            match outcome {
+                Ok(()) => {}
+                Err(PolicyError::Failed { error, .. }) => {
+                    let _ = events.send(TaskEvent::Error(error));
+                }
+                Err(PolicyError::TimedOut { .. }) => {
+                    let _ = events.send(TaskEvent::Cancelled(CancelReason::Timeout));
+                }
+            }
+        })
+    }
+}
+
+// 登记的任务及其尚未被取走的事件数量，用于限制所有任务缓冲的事件总量
+// 每个 JobTask 创建时都会登记到 TaskRegistry::global()，也可以通过 with_registry 额外登记到独立的注册表
+// 任务的所有 JobTask 句柄都被释放后自动从注册表中移除
+#[derive(Clone, Default)]
+pub struct TaskRegistry {
+    tasks: Arc<Mutex<Vec<Box<dyn TrackedTask>>>>,
+}
+
+trait TrackedTask: Send {
+    fn buffered(&self) -> usize;
+    fn is_alive(&self) -> bool;
+    fn is_cancelled(&self) -> bool;
+    fn cancel(&self, reason: CancelReason);
+}
+
+// 不持有 Receiver 本身，否则任务的所有句柄释放后通道仍然连着，阻塞在 send 上的任务无法结束
+struct Tracked<T, E> {
+    event_rx: Weak<Receiver<TaskEvent<T, E>>>,
+    handle: Weak<JoinHandle<()>>,
+    is_cancelled: Arc<AtomicBool>,
+    cancel_reason: Arc<Mutex<Option<CancelReason>>>,
+}
+
+impl<T: Send, E: Send> TrackedTask for Tracked<T, E> {
+    fn buffered(&self) -> usize {
+        self.event_rx.upgrade().map_or(0, |event_rx| event_rx.len())
+    }
+
+    fn is_alive(&self) -> bool {
+        self.handle.strong_count() > 0 && self.event_rx.strong_count() > 0
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.is_cancelled.load(Ordering::Acquire)
+    }
+
+    fn cancel(&self, reason: CancelReason) {
+        self.cancel_reason.lock().unwrap().get_or_insert(reason);
+        self.is_cancelled.store(true, Ordering::Release);
+        if let Some(handle) = self.handle.upgrade() {
+            unsafe { handle.coroutine().cancel() };
+        }
+    }
+}
+
+static GLOBAL_REGISTRY: Lazy<TaskRegistry> = Lazy::new(TaskRegistry::default);
+
+impl TaskRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // 登记了进程内所有 JobTask 的注册表
+    pub fn global() -> TaskRegistry {
+        GLOBAL_REGISTRY.clone()
+    }
+
+    fn track<T: Send + 'static, E: Send + 'static, D: Send + 'static, C: Send + 'static>(&self, job: &JobTask<T, E, D, C>) {
+        let Some(handle) = &job.handle else {
+            return;
+        };
+        // 登记前先移除已经被释放的任务，避免长期不查询的注册表无限增长
+        self.live().push(Box::new(Tracked {
+            event_rx: Arc::downgrade(&job.event_rx),
+            handle: Arc::downgrade(handle),
+            is_cancelled: job.is_cancelled.clone(),
+            cancel_reason: job.cancel_reason.clone(),
+        }));
+    }
+
+    // 取出仍然存活的任务，顺便移除已经被释放的任务
+    fn live(&self) -> std::sync::MutexGuard<'_, Vec<Box<dyn TrackedTask>>> {
+        let mut tasks = self.tasks.lock().unwrap();
+        tasks.retain(|task| task.is_alive());
+        tasks
+    }
+
+    // 登记的存活任务数量
+    pub fn len(&self) -> usize {
+        self.live().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    // 未被取消的任务缓冲的事件总数；被取消的任务不再产生事件，不计入
+    pub fn total_buffered(&self) -> usize {
+        self.live().iter().filter(|task| !task.is_cancelled()).map(|task| task.buffered()).sum()
+    }
+
+    // 总数超过 max_total 时，从缓冲最多的任务开始取消，直到不超过上限；返回取消的任务数
+    pub fn prune(&self, max_total: usize) -> usize {
+        let tasks = self.live();
+        let mut candidates = tasks
+            .iter()
+            .filter(|task| !task.is_cancelled())
+            .map(|task| (task.buffered(), task))
+            .collect::<Vec<_>>();
+        let mut total = candidates.iter().map(|(buffered, _)| buffered).sum::<usize>();
+        candidates.sort_by_key(|(buffered, _)| std::cmp::Reverse(*buffered));
+        let mut cancelled = 0;
+        for (buffered, task) in candidates {
+            if total <= max_total {
+                break;
+            }
+            task.cancel(CancelReason::Pruned);
+            total -= buffered;
+            cancelled += 1;
+        }
+        cancelled
+    }
+}
+
+struct LatestData<T, E> {
+    event_rx: Receiver<TaskEvent<T, E>>,
+    latest: Arc<Mutex<Option<T>>>,
+}
+
+impl<T, E> Clone for LatestData<T, E> {
+    fn clone(&self) -> Self {
+        Self {
+            event_rx: self.event_rx.clone(),
+            latest: self.latest.clone(),
+        }
+    }
 }
 
-impl <T, E, D>  Drop for JobTask<T, E, D>
+impl<T: Clone, E> Service<(), Option<T>> for LatestData<T, E> {
+    fn call(&self, _: ()) -> Option<T> {
+        let mut latest = self.latest.lock().unwrap();
+        for event in self.event_rx.try_iter() {
+            if let TaskEvent::Data(data) = event {
+                *latest = Some(data);
+            }
+        }
+        latest.clone()
+    }
+}
+
+impl<T, E> TaskEvent<T, E> {
+    // 之后不会再有事件的终止事件；Error 之后任务返回时仍会发送 Done
+    pub fn is_terminal(&self) -> bool {
+        matches!(self, TaskEvent::Done | TaskEvent::Cancelled(_) | TaskEvent::Panic(_))
+    }
+}
+
+// 以迭代器的方式拉取任务事件，收到终止事件后结束
+pub struct TaskIter<T, E> {
+    next: Box<dyn FnMut() -> Option<TaskEvent<T, E>>>,
+    finished: bool,
+}
+
+impl<T, E> Iterator for TaskIter<T, E> {
+    type Item = TaskEvent<T, E>;
+
+    // 阻塞直到下一个事件到达
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+        let event = (self.next)();
+        self.finished = event.as_ref().map_or(true, TaskEvent::is_terminal);
+        event
+    }
+}
+
+impl<T: Send + 'static, E: Send + 'static, D: Send + 'static, C: Send + 'static> IntoIterator for JobTask<T, E, D, C> {
+    type Item = TaskEvent<T, E>;
+    type IntoIter = TaskIter<T, E>;
+
+    // 迭代器持有任务，迭代器被丢弃时任务随之取消
+    fn into_iter(self) -> Self::IntoIter {
+        TaskIter {
+            next: Box::new(move || self.recv()),
+            finished: false,
+        }
+    }
+}
+
+impl <T, E, D, C>  Drop for JobTask<T, E, D, C>
 where
     T: Send  + 'static,
     E: Send  + 'static,
-    D: Send  + 'static
+    D: Send  + 'static,
+    C: Send  + 'static
 {
     fn drop(&mut self) {
         self.cancel(); // 确保任务被清理
@@ -146,11 +731,12 @@ mod tests {
                 match event {
                     TaskEvent::Data(v) => println!("{}", v),
                     TaskEvent::Done => println!("Task completed"),
-                    TaskEvent::Cancelled => println!("Task cancelled"),
+                    TaskEvent::Cancelled(reason) => println!("Task cancelled: {:?}", reason),
                     TaskEvent::Error(e) => println!("Error: {}", e),
+                    TaskEvent::SetupFailed(e) => println!("Setup failed: {}", e),
                     TaskEvent::Panic(p) => println!("Panic: {}", p),
                     TaskEvent::Progress(p) => {
-                        println!("Progress: {}", p.0);
+                        println!("Progress: {}", p.percent);
                     }
                 }
             }
@@ -167,6 +753,371 @@ mod tests {
         println!("Main thread finished.");
     }
 
+    #[test]
+    fn test_resume_from_checkpoint() {
+        let mut job: JobTask<u64, String, ()> = JobTask::new_resumable(json!({}), None, |_, ctx, _, _| {
+            let mut step = ctx.last_checkpoint().and_then(|v| v.as_u64()).unwrap_or(0);
+            loop {
+                step += 1;
+                ctx.checkpoint(json!(step));
+                may::coroutine::sleep(Duration::from_millis(10));
+            }
+        });
+
+        while job.last_checkpoint().and_then(|v| v.as_u64()).unwrap_or(0) < 3 {
+            thread::sleep(Duration::from_millis(10));
+        }
+        job.cancel();
+        let checkpoint = job.last_checkpoint().unwrap();
+
+        let resumed: JobTask<u64, String, ()> = JobTask::new_resumable(json!({}), Some(checkpoint.clone()), |_, ctx, sender, _| {
+            let step = ctx.last_checkpoint().and_then(|v| v.as_u64()).unwrap();
+            sender.send(TaskEvent::Data(step)).unwrap();
+        });
+        match resumed.recv_timeout(Duration::from_secs(5)) {
+            Some(TaskEvent::Data(step)) => assert_eq!(Some(step), checkpoint.as_u64()),
+            other => panic!("unexpected event: {:?}", other),
+        }
+        assert_eq!(resumed.last_checkpoint(), Some(checkpoint));
+    }
+
+    #[test]
+    fn test_setup_failed_before_start() {
+        let job: JobTask<u32, String, ()> = JobTask::new(json!({}), |_, sender, _| {
+            sender.send(TaskEvent::Error("missing credentials".to_string())).unwrap();
+        });
+        match job.recv_timeout(Duration::from_secs(5)) {
+            Some(TaskEvent::SetupFailed(e)) => assert_eq!(e, "missing credentials"),
+            other => panic!("unexpected event: {:?}", other),
+        }
+        assert!(matches!(job.recv_timeout(Duration::from_secs(5)), Some(TaskEvent::Done)));
+
+        let job: JobTask<u32, String, ()> = JobTask::new(json!({}), |_, sender, _| {
+            sender.send(TaskEvent::Data(1)).unwrap();
+            sender.send(TaskEvent::Error("connection lost".to_string())).unwrap();
+        });
+        assert!(matches!(job.recv_timeout(Duration::from_secs(5)), Some(TaskEvent::Data(1))));
+        match job.recv_timeout(Duration::from_secs(5)) {
+            Some(TaskEvent::Error(e)) => assert_eq!(e, "connection lost"),
+            other => panic!("unexpected event: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_setup_failed_after_latest_service_drained_data() {
+        let (release_tx, release_rx) = channel::unbounded::<()>();
+        let job: JobTask<u32, String, ()> = JobTask::new(json!({}), move |_, sender, _| {
+            sender.send(TaskEvent::Data(1)).unwrap();
+            let _ = release_rx.recv();
+            sender.send(TaskEvent::Error("connection lost".to_string())).unwrap();
+        });
+        // Data 被 latest_service 取走，没有经过 recv
+        let latest = job.latest_service();
+        let deadline = std::time::Instant::now() + Duration::from_secs(5);
+        while latest.call(()).is_none() {
+            assert!(std::time::Instant::now() < deadline, "task did not send its data");
+            thread::sleep(Duration::from_millis(5));
+        }
+        release_tx.send(()).unwrap();
+        match job.recv_timeout(Duration::from_secs(5)) {
+            Some(TaskEvent::Error(e)) => assert_eq!(e, "connection lost"),
+            other => panic!("unexpected event: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_into_iter_ends_after_done() {
+        let job: JobTask<u32, String, ()> = JobTask::new(json!({}), |_, sender, _| {
+            for i in 1..=3 {
+                sender.send(TaskEvent::Data(i)).unwrap();
+            }
+            sender.send(TaskEvent::Progress(Progress::new(3, 3))).unwrap();
+        });
+
+        let mut data = Vec::new();
+        let mut events = job.into_iter();
+        for event in &mut events {
+            match event {
+                TaskEvent::Data(i) => data.push(i),
+                TaskEvent::Progress(_) => {}
+                TaskEvent::Done => data.push(0),
+                other => panic!("unexpected event: {:?}", other),
+            }
+        }
+        assert_eq!(data, vec![1, 2, 3, 0]);
+        assert!(events.next().is_none());
+    }
+
+    #[test]
+    fn test_latest_service() {
+        let job: JobTask<u32, String, ()> = JobTask::new(json!({}), |_, sender, _| {
+            for i in 1..=5 {
+                sender.send(TaskEvent::Data(i)).unwrap();
+                may::coroutine::sleep(Duration::from_millis(20));
+            }
+        });
+        let latest = job.latest_service();
+        let observer = latest.clone();
+
+        let mut seen = Vec::new();
+        let deadline = std::time::Instant::now() + Duration::from_secs(5);
+        while seen.last() != Some(&5) && std::time::Instant::now() < deadline {
+            if let Some(value) = latest.call(()) {
+                seen.push(value);
+            }
+            thread::sleep(Duration::from_millis(5));
+        }
+        assert_eq!(seen.last(), Some(&5));
+        assert!(seen.windows(2).all(|w| w[0] <= w[1]));
+        // 克隆共享同一个缓存
+        assert_eq!(observer.call(()), Some(5));
+    }
+
+    #[test]
+    fn test_with_capacity_blocks_producer() {
+        use std::sync::atomic::AtomicUsize;
+
+        let produced = Arc::new(AtomicUsize::new(0));
+        let count = produced.clone();
+        let job: JobTask<usize, String, ()> = JobTask::with_capacity(json!({}), 2, move |_, sender, _| {
+            for i in 0..100 {
+                let _ = sender.send(TaskEvent::Data(i));
+                count.fetch_add(1, Ordering::SeqCst);
+            }
+        });
+
+        // 不取事件：生产者填满通道后阻塞
+        std::thread::sleep(Duration::from_millis(100));
+        assert_eq!(produced.load(Ordering::SeqCst), 2);
+
+        let events: Vec<_> = job.into_iter().collect();
+        assert_eq!(events.len(), 101);
+        assert!(matches!(events.last(), Some(TaskEvent::Done)));
+        assert_eq!(produced.load(Ordering::SeqCst), 100);
+    }
+
+    #[test]
+    fn test_dropped_handle_releases_blocked_producer() {
+        // 任务结束（正常返回或被取消展开）时设置标志
+        struct Finished(Arc<AtomicBool>);
+        impl Drop for Finished {
+            fn drop(&mut self) {
+                self.0.store(true, Ordering::SeqCst);
+            }
+        }
+        let finished = Arc::new(AtomicBool::new(false));
+        let guard = Finished(finished.clone());
+        let job: JobTask<usize, String, ()> = JobTask::with_capacity(json!({}), 1, move |_, sender, _| {
+            let _guard = guard;
+            let mut i = 0;
+            while sender.send(TaskEvent::Data(i)).is_ok() {
+                i += 1;
+            }
+        });
+        assert!(!TaskRegistry::global().is_empty());
+        std::thread::sleep(Duration::from_millis(50));
+        assert!(!finished.load(Ordering::SeqCst));
+
+        // 全局注册表不持有事件通道，释放句柄后生产者不再阻塞
+        drop(job);
+        let deadline = std::time::Instant::now() + Duration::from_secs(5);
+        while !finished.load(Ordering::SeqCst) {
+            assert!(std::time::Instant::now() < deadline, "producer stayed blocked after its handle was dropped");
+            thread::sleep(Duration::from_millis(5));
+        }
+    }
+
+    #[test]
+    fn test_control_channel() {
+        enum Control {
+            Reset,
+            Stop,
+        }
+        let job = JobTask::<u32, String, u32>::builder(json!({}))
+            .with_control_channel::<Control>()
+            .spawn(|_, sender, data, control| {
+                let mut sum = 0;
+                loop {
+                    // 控制消息优先于数据
+                    match control.try_recv() {
+                        Ok(Control::Reset) => {
+                            sum = 0;
+                            sender.send(TaskEvent::Data(sum)).unwrap();
+                        }
+                        Ok(Control::Stop) => return,
+                        Err(_) => {}
+                    }
+                    if let Ok(n) = data.try_recv() {
+                        sum += n;
+                        sender.send(TaskEvent::Data(sum)).unwrap();
+                    }
+                    may::coroutine::sleep(Duration::from_millis(5));
+                }
+            });
+        let next = || match job.recv_timeout(Duration::from_secs(5)) {
+            Some(TaskEvent::Data(sum)) => Some(sum),
+            Some(TaskEvent::Done) => None,
+            other => panic!("unexpected event: {:?}", other),
+        };
+
+        job.send(1);
+        assert_eq!(next(), Some(1));
+        job.send(2);
+        assert_eq!(next(), Some(3));
+        job.send_control(Control::Reset);
+        assert_eq!(next(), Some(0));
+        job.send(5);
+        assert_eq!(next(), Some(5));
+        job.send_control(Control::Stop);
+        assert_eq!(next(), None);
+    }
+
+    #[test]
+    fn test_cancel_reasons() {
+        fn wait_cancelled(job: &JobTask<u32, String, ()>) -> CancelReason {
+            loop {
+                match job.recv_timeout(Duration::from_secs(5)) {
+                    Some(TaskEvent::Cancelled(reason)) => return reason,
+                    Some(_) => continue,
+                    None => panic!("task was not cancelled"),
+                }
+            }
+        }
+        let looping = |_, _: TaskSender<u32, String>, _| loop {
+            may::coroutine::sleep(Duration::from_millis(10));
+        };
+
+        let job: JobTask<u32, String, ()> = JobTask::new(json!({}), looping);
+        job.cancel_after(Duration::from_millis(50));
+        assert_eq!(wait_cancelled(&job), CancelReason::Timeout);
+        assert_eq!(job.cancel_reason(), Some(CancelReason::Timeout));
+
+        let mut job: JobTask<u32, String, ()> = JobTask::new(json!({}), looping);
+        thread::sleep(Duration::from_millis(20));
+        job.cancel();
+        assert_eq!(wait_cancelled(&job), CancelReason::Requested);
+
+        let mut job: JobTask<u32, String, ()> = JobTask::new(json!({}), looping);
+        job.cancel_with(CancelReason::Parent);
+        assert_eq!(wait_cancelled(&job), CancelReason::Parent);
+    }
+
+    #[test]
+    fn test_join() {
+        let job: JobTask<u32, String, ()> = JobTask::new(json!({}), |_, sender, _| {
+            for i in 1..=3 {
+                let _ = sender.send(TaskEvent::Data(i));
+            }
+            let _ = sender.send(TaskEvent::Progress(Progress::new(3, 3)));
+        });
+        assert_eq!(job.join(), TaskOutcome::Completed(vec![1, 2, 3]));
+
+        let job: JobTask<u32, String, ()> = JobTask::new(json!({}), |_, sender, _| {
+            let _ = sender.send(TaskEvent::Data(1));
+            let _ = sender.send(TaskEvent::Error("bad input".to_string()));
+        });
+        assert_eq!(job.join(), TaskOutcome::Failed("bad input".to_string()));
+
+        let job: JobTask<u32, String, ()> = JobTask::new(json!({}), |_, _, _| {
+            panic!("lost connection to {}", "db");
+        });
+        assert_eq!(job.join(), TaskOutcome::Panicked("lost connection to db".to_string()));
+    }
+
+    #[test]
+    fn test_progress_clamped() {
+        assert_eq!(Progress::new(1, 4), Progress { percent: 25, current: 1, total: 4 });
+        assert_eq!(Progress::new(7, 4).percent, 100);
+        assert_eq!(Progress::new(3, 0).percent, 0);
+        assert_eq!(Progress::from_percent(150), Progress { percent: 100, current: 100, total: 100 });
+    }
+
+    #[test]
+    fn test_last_progress() {
+        let job: JobTask<(), String, ()> = JobTask::new(json!({}), |_, sender, _| {
+            for current in [1, 3, 2] {
+                let _ = sender.send(TaskEvent::Progress(Progress::new(current, 4)));
+            }
+            // 直接构造的越界百分比也会被限制
+            let _ = sender.send(TaskEvent::Progress(Progress { percent: 200, current: 4, total: 4 }));
+        });
+        assert_eq!(job.last_progress(), None);
+
+        let mut seen = Vec::new();
+        while let Some(event) = job.recv() {
+            match event {
+                TaskEvent::Progress(progress) => {
+                    seen.push(progress.percent);
+                    if progress.current == 2 {
+                        // 回退的进度仍然送达，但不会覆盖缓存
+                        assert_eq!(job.last_progress(), Some(Progress::new(3, 4)));
+                    }
+                }
+                TaskEvent::Done => break,
+                other => panic!("unexpected event: {:?}", other),
+            }
+        }
+        assert_eq!(seen, vec![25, 75, 50, 100]);
+        assert_eq!(job.last_progress(), Some(Progress { percent: 100, current: 4, total: 4 }));
+    }
+
+    #[test]
+    fn test_new_with_deadline() {
+        // 不让出协程的循环，只能通过取消标志结束
+        let job: JobTask<u32, String, ()> =
+            JobTask::new_with_deadline(json!({}), Duration::from_secs(1), |_, cancelled, sender, _| {
+                let mut ticks = 0;
+                while !cancelled.load(Ordering::Acquire) {
+                    ticks += 1;
+                    let _ = sender.send(TaskEvent::Data(ticks));
+                    thread::sleep(Duration::from_millis(10));
+                }
+            });
+
+        let started = std::time::Instant::now();
+        let last = job.into_iter().last();
+        assert!(matches!(last, Some(TaskEvent::Cancelled(CancelReason::Timeout))));
+        assert!(started.elapsed() >= Duration::from_millis(900));
+    }
+
+    #[test]
+    fn test_registry_prunes_most_buffered() {
+        let registry = TaskRegistry::new();
+        // 先发出 n 个事件，之后一直运行直到被取消
+        let spawn = |n: u32| -> JobTask<u32, String, ()> {
+            JobTask::builder(json!({})).with_registry(&registry).spawn(move |_, sender, _, _| {
+                for i in 0..n {
+                    let _ = sender.send(TaskEvent::Data(i));
+                }
+                loop {
+                    may::coroutine::sleep(Duration::from_millis(10));
+                }
+            })
+        };
+        let small = spawn(5);
+        let medium = spawn(10);
+        let large = spawn(20);
+        assert_eq!(registry.len(), 3);
+
+        let started = std::time::Instant::now();
+        while registry.total_buffered() < 35 {
+            assert!(started.elapsed() < Duration::from_secs(5), "tasks did not buffer their events");
+            thread::sleep(Duration::from_millis(5));
+        }
+
+        assert_eq!(registry.prune(50), 0);
+        assert_eq!(registry.prune(12), 2);
+        assert!(registry.total_buffered() <= 12);
+        assert_eq!(large.cancel_reason(), Some(CancelReason::Pruned));
+        assert_eq!(medium.cancel_reason(), Some(CancelReason::Pruned));
+        assert!(!small.is_cancelled());
+
+        // 释放句柄后任务从注册表中移除
+        drop(large);
+        drop(medium);
+        assert_eq!(registry.len(), 1);
+    }
+
     #[test]
     fn test_generator() {
         let g = Gn::new_scoped(|mut s| {
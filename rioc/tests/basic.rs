@@ -52,4 +52,21 @@ fn test_basic_inject() {
     let cc = provider.provide::<ControllerWithInject>();
     assert_eq!(cc.value, 200);
     cc.dep.welcome();
-}
\ No newline at end of file
+}
+#[injectable]
+pub struct ControllerWithSelfInject {
+    #[inject(Self::default_value())]
+    value: i32,
+}
+
+impl ControllerWithSelfInject {
+    fn default_value() -> i32 {
+        300
+    }
+}
+
+#[test]
+fn test_basic_inject_self_expression() {
+    let cc = InitProvider.provide::<ControllerWithSelfInject>();
+    assert_eq!(cc.value, 300);
+}
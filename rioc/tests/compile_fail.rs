@@ -0,0 +1,12 @@
+#[test]
+fn inject_with_mismatched_expression_should_fail_at_the_attribute() {
+    let t = trybuild::TestCases::new();
+    t.compile_fail("tests/ui/inject_type_mismatch.rs");
+}
+
+#[test]
+fn assert_provides_should_fail_only_when_a_binding_is_missing() {
+    let t = trybuild::TestCases::new();
+    t.pass("tests/ui/assert_provides_pass.rs");
+    t.compile_fail("tests/ui/assert_provides_missing.rs");
+}
@@ -0,0 +1,80 @@
+use rioc::{ContextPoolStats, Direction, LayerBuilder, LayerChain, LayerResult, PayLoad};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+// Counts every allocation made by the test binary.
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+// A chain whose single layer fills the request context, failing if it sees data from an earlier request.
+fn chain() -> LayerChain {
+    let layer = LayerBuilder::new()
+        .with_inbound_fn(|req| {
+            let mut req = req.ok_or("missing payload")?;
+            if req.ctx.data.len() != 1 || req.ctx.deadline.is_some() {
+                return Err("context leaked from a previous request".into());
+            }
+            for key in ["user", "role", "tenant", "region"] {
+                req.ctx.data.insert(key.to_string(), String::new());
+            }
+            Ok(LayerResult { direction: Direction::Inbound, data: Some(req) })
+        })
+        .with_outbound_passthrough()
+        .build()
+        .unwrap();
+    let mut chain = LayerChain::new();
+    chain.add_layer(layer);
+    chain
+}
+
+fn request() -> PayLoad {
+    let mut req = PayLoad::empty();
+    req.ctx.data.insert("id".to_string(), String::new());
+    req
+}
+
+// Allocations made while sending `count` requests through the chain, handing each result back.
+fn allocations(chain: &LayerChain, count: usize) -> usize {
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    for _ in 0..count {
+        let result = chain.handle_inbound(Some(request())).unwrap();
+        if let Some(payload) = result.data {
+            chain.recycle(payload);
+        }
+    }
+    ALLOCATIONS.load(Ordering::Relaxed) - before
+}
+
+#[test]
+fn context_pool_should_reuse_cleared_contexts_across_requests() {
+    // Given
+    let plain = chain();
+    let pooled = chain().with_context_pool(1);
+    // When
+    let plain_allocations = allocations(&plain, 100);
+    let pooled_allocations = allocations(&pooled, 100);
+    // Then
+    assert_eq!(pooled.context_pool_stats(), Some(ContextPoolStats { created: 1, reused: 99, idle: 1 }));
+    // The pooled map keeps its capacity, so nearly every request skips growing it for the layer's keys.
+    assert!(
+        pooled_allocations + 90 <= plain_allocations,
+        "pooled: {}, plain: {}",
+        pooled_allocations,
+        plain_allocations
+    );
+}
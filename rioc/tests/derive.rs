@@ -0,0 +1,70 @@
+use rioc::{provider, Direction, Injectable, LayerChain, LayerResult, PayLoad, ProtocolLayer};
+
+#[derive(Debug, PartialEq)]
+pub struct Dep {
+    value: i32,
+}
+
+#[derive(Injectable)]
+pub struct StructWithNamedFields {
+    dep: Dep,
+    #[inject(String::from("from expression"))]
+    name: String,
+}
+
+#[derive(Injectable)]
+pub struct StructWithUnnamedFields(Dep, #[inject(7)] i32);
+
+#[provider]
+#[provide(Dep, Dep { value: 42 })]
+struct Provider;
+
+#[test]
+fn derive_with_named_fields_should_inject_from_provider_and_expression() {
+    // When
+    let facade = Provider.provide::<StructWithNamedFields>();
+    // Then
+    assert_eq!(facade.dep, Dep { value: 42 });
+    assert_eq!(facade.name, "from expression");
+}
+
+#[test]
+fn derive_with_unnamed_fields_should_inject_from_provider_and_expression() {
+    // When
+    let facade = Provider.provide::<StructWithUnnamedFields>();
+    // Then
+    assert_eq!(facade.0, Dep { value: 42 });
+    assert_eq!(facade.1, 7);
+}
+
+#[derive(ProtocolLayer)]
+struct Framing {
+    open: char,
+    close: char,
+}
+
+impl Framing {
+    fn inbound(&self, req: PayLoad) -> Result<LayerResult, String> {
+        let data = req.data.map(|d| d.trim_start_matches(self.open).trim_end_matches(self.close).to_string());
+        Ok(LayerResult { direction: Direction::Inbound, data: Some(PayLoad { data, ..req }) })
+    }
+
+    fn outbound(&self, req: PayLoad) -> Result<LayerResult, String> {
+        let data = req.data.map(|d| format!("{}{}{}", self.open, d, self.close));
+        Ok(LayerResult { direction: Direction::Outbound, data: Some(PayLoad { data, ..req }) })
+    }
+}
+
+#[test]
+fn derive_protocol_layer_should_wire_both_handlers_into_a_layer() {
+    // Given
+    let mut chain = LayerChain::new();
+    chain.add_layer(Framing { open: '[', close: ']' }.into_layer());
+    // When
+    let inbound = chain.handle_inbound(Some(PayLoad::text("[hello]"))).unwrap();
+    let outbound = chain.handle_outbound(Some(PayLoad::text("world"))).unwrap();
+    // Then
+    assert_eq!(inbound.data.unwrap().data.as_deref(), Some("hello"));
+    assert_eq!(outbound.data.unwrap().data.as_deref(), Some("[world]"));
+    assert_eq!(chain.inspect()[0].name.as_deref(), Some("Framing"));
+}
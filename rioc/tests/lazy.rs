@@ -0,0 +1,35 @@
+use rioc::{injectable, provider, Lazy};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+static BUILT: AtomicUsize = AtomicUsize::new(0);
+
+fn build_expensive() -> i32 {
+    BUILT.fetch_add(1, Ordering::SeqCst);
+    123
+}
+
+#[injectable]
+struct ExpensiveDep(#[inject(build_expensive())] i32);
+
+#[injectable]
+struct Facade<'a> {
+    dep: Lazy<'a, ExpensiveDep>,
+}
+
+#[provider]
+struct Provider;
+
+#[test]
+fn provide_lazy_dep_should_build_it_on_first_access_only() {
+    // Given
+    let facade: Facade = Provider.provide();
+    assert_eq!(BUILT.load(Ordering::SeqCst), 0);
+    assert!(!facade.dep.is_initialized());
+    // When
+    let first = facade.dep.get().0;
+    let second = facade.dep.0;
+    // Then
+    assert_eq!((first, second), (123, 123));
+    assert_eq!(BUILT.load(Ordering::SeqCst), 1);
+    assert!(facade.dep.is_initialized());
+}
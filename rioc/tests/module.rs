@@ -1,5 +1,5 @@
 use crate::sub::Greeter;
-use rioc::{injectable, module, provider};
+use rioc::{injectable, module, provider, Import, ProviderManifest};
 use std::rc::Rc;
 
 #[provider]
@@ -175,6 +175,86 @@ fn provide_with_module_with_factory_internal_export_should_provide_its_members_c
     assert_eq!(*dep_ref.0, *provider.0 .1 .0);
 }
 
+#[test]
+fn import_resolve_with_imported_module_should_resolve_its_exports() {
+    // Given
+    #[injectable]
+    #[provider]
+    struct Provider(#[import] sub::SimpleModule);
+    let provider = InitProvider.provide::<Provider>();
+    // When
+    let facade = provider.import_resolve::<sub::SimpleFacade>();
+    // Then
+    assert_eq!(facade, Some(sub::expected_simple_facade(&provider.0)))
+}
+
+#[test]
+fn import_resolve_with_manual_import_should_resolve_its_exports() {
+    // Given
+    struct Host(sub::DynDepModule);
+    impl Import<sub::DynDepModule> for Host {
+        fn reference(&self) -> &sub::DynDepModule {
+            &self.0
+        }
+    }
+    let host = Host(InitProvider.provide());
+    // When
+    let dep = host.import_resolve::<&dyn sub::Greeter>();
+    // Then
+    assert_eq!(dep.map(|dep| dep.greet()), Some(sub::GreeterOne.greet()))
+}
+
+#[test]
+fn provider_manifest_should_list_declared_provides_and_imports() {
+    // Given
+    #[injectable]
+    #[provider]
+    #[provide(i32, 123)]
+    #[provide(Box<dyn sub::Greeter>, |g: sub::GreeterOne| Box::new(g))]
+    struct Provider<'a> {
+        #[import]
+        simple: sub::SimpleModule,
+        #[import]
+        with_ref: &'a sub::ModuleWithRef<'a>,
+        #[provide]
+        #[provide(Rc<i32>, |x| x.clone())]
+        shared: Rc<i32>,
+    }
+    // When
+    let provides = Provider::provides();
+    let imports = Provider::imports();
+    // Then
+    assert_eq!(
+        provides,
+        vec!["i32", "Box<dyn sub::Greeter>", "&Rc<i32>", "Rc<i32>"]
+    );
+    assert_eq!(imports, vec!["sub::SimpleModule", "sub::ModuleWithRef"]);
+}
+
+#[test]
+fn provider_skip_should_exclude_field_from_provider_codegen() {
+    // Given
+    #[provider]
+    struct Provider {
+        #[provide]
+        name: String,
+        #[provider_skip]
+        #[provide]
+        cache: Rc<i32>,
+    }
+    let provider = Provider {
+        name: "rioc".to_string(),
+        cache: Rc::new(123),
+    };
+    // When
+    let name = provider.provide::<&String>();
+    let provides = Provider::provides();
+    // Then
+    assert_eq!(name, "rioc");
+    assert_eq!(*provider.cache, 123);
+    assert_eq!(provides, vec!["&String"]);
+}
+
 mod sub {
     use rioc::{injectable, module};
     use rioc::inject;
@@ -12,7 +12,7 @@ fn provide_with_type_providable_by_root_should_be_providable_by_scope() {
     // When
     let value = scope.provide::<i32>();
     // Then
-    assert_eq!(value, Root.provide());
+    assert_eq!(value, Root.provide::<i32>());
 }
 
 #[test]
@@ -57,7 +57,7 @@ fn provide_with_scope_type_providable_by_root_should_expose_a_ref_to_the_type_in
     // When
     let value = scope.provide::<&i32>();
     // Then
-    assert_eq!(*value, Root.provide());
+    assert_eq!(*value, Root.provide::<i32>());
 }
 
 #[test]
@@ -224,6 +224,27 @@ fn provide_with_named_scope_should_use_corresponding_scope() {
     assert_eq!(value, ScopeDep(&Integer(123), &2, "V1"));
 }
 
+#[test]
+fn drop_scope_with_provided_task_should_cancel_the_task() {
+    // Given
+    #[provider]
+    #[provide(Task, Task::new(serde_json::Value::Null, |_, _, _| loop {
+        may::coroutine::sleep(std::time::Duration::from_millis(10));
+    }))]
+    #[scope(Task)]
+    struct Root;
+
+    let scope = Root.scope();
+    let task = scope.provide::<&Task>().clone();
+    assert!(!task.is_cancelled());
+    // When
+    drop(scope);
+    // Then
+    assert!(task.is_cancelled());
+}
+
+type Task = rioc::JobTask<i32, String, i32>;
+
 trait IntegerOwner {
     fn value(&self) -> i32;
 }
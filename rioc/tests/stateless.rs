@@ -163,6 +163,29 @@ fn provide_struct_with_inject_factory_and_additionnal_injectable_inputs_on_field
     );
 }
 
+#[test]
+fn provide_struct_with_factory_inject_attr_should_create_a_new_dep_on_each_call() {
+    // Given
+    #[provider]
+    #[provide(Ticket, Ticket(self.0.replace(self.0.get() + 1)))]
+    struct CountingProvider(std::cell::Cell<i32>);
+    #[derive(Debug, PartialEq)]
+    struct Ticket(i32);
+    #[injectable]
+    struct Consumer<'a> {
+        #[inject(factory)]
+        tickets: Box<dyn Fn() -> Ticket + 'a>,
+    }
+    let provider = CountingProvider(std::cell::Cell::new(1));
+    // When
+    let consumer: Consumer = provider.provide();
+    let first = (consumer.tickets)();
+    let second = (consumer.tickets)();
+    // Then
+    assert_eq!(first, Ticket(1));
+    assert_eq!(second, Ticket(2));
+}
+
 #[injectable]
 #[derive(Debug, PartialEq)]
 struct StructWithNonInjectableNamedDeps {
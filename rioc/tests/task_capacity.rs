@@ -0,0 +1,36 @@
+use rioc::{JobTask, TaskEvent};
+use serde_json::json;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+// The only test in this binary: it runs the may scheduler on a single worker thread.
+#[test]
+fn with_capacity_producer_should_leave_the_worker_to_other_coroutines() {
+    // Given
+    may::config().set_workers(1);
+    let produced = Arc::new(AtomicUsize::new(0));
+    let count = produced.clone();
+    let job: JobTask<usize, String, ()> = JobTask::with_capacity(json!({}), 1, move |_, sender, _| {
+        for i in 0..10 {
+            let _ = sender.send(TaskEvent::Data(i));
+            count.fetch_add(1, Ordering::SeqCst);
+        }
+    });
+    while produced.load(Ordering::SeqCst) < 1 {
+        std::thread::sleep(Duration::from_millis(5));
+    }
+    // When
+    let (tx, rx) = std::sync::mpsc::channel();
+    let _other = unsafe {
+        may::coroutine::spawn(move || {
+            let _ = tx.send(());
+        })
+    };
+    // Then
+    rx.recv_timeout(Duration::from_secs(5)).expect("blocked producer starved the only worker");
+    assert_eq!(produced.load(Ordering::SeqCst), 1);
+    let events: Vec<_> = job.into_iter().collect();
+    assert_eq!(events.len(), 11);
+    assert!(matches!(events.last(), Some(TaskEvent::Done)));
+}
@@ -0,0 +1,36 @@
+use rioc::{CancelReason, JobTask, TaskEvent, TaskRegistry};
+use serde_json::json;
+use std::time::{Duration, Instant};
+
+// Sends `n` events, then keeps running until cancelled.
+fn spawn(n: u32) -> JobTask<u32, String, ()> {
+    JobTask::new(json!({}), move |_, sender, _| {
+        for i in 0..n {
+            let _ = sender.send(TaskEvent::Data(i));
+        }
+        loop {
+            may::coroutine::sleep(Duration::from_millis(10));
+        }
+    })
+}
+
+#[test]
+fn global_registry_should_track_every_task_on_creation() {
+    // Given
+    let registry = TaskRegistry::global();
+    let small = spawn(5);
+    let large = spawn(20);
+    // When
+    let started = Instant::now();
+    while registry.total_buffered() < 25 {
+        assert!(started.elapsed() < Duration::from_secs(5), "tasks did not buffer their events");
+        std::thread::sleep(Duration::from_millis(5));
+    }
+    // Then
+    assert_eq!(registry.len(), 2);
+    assert_eq!(registry.prune(10), 1);
+    assert_eq!(large.cancel_reason(), Some(CancelReason::Pruned));
+    assert!(!small.is_cancelled());
+    drop(large);
+    assert_eq!(registry.len(), 1);
+}
@@ -0,0 +1,15 @@
+use rioc::{assert_provides, injectable, provider};
+
+#[injectable]
+struct FacadeA(i32);
+
+#[injectable]
+struct FacadeB(String);
+
+#[provider]
+#[provide(i32, 42)]
+struct Provider;
+
+assert_provides!(Provider => [FacadeA, FacadeB]);
+
+fn main() {}
@@ -0,0 +1,16 @@
+use rioc::{assert_provides, injectable, provider};
+
+#[injectable]
+struct FacadeA(i32);
+
+#[injectable]
+struct FacadeB(String);
+
+#[provider]
+#[provide(i32, 42)]
+#[provide(String, "name".to_string())]
+struct Provider;
+
+assert_provides!(Provider => [FacadeA, FacadeB]);
+
+fn main() {}
@@ -0,0 +1,9 @@
+use rioc::injectable;
+
+#[injectable]
+struct Config {
+    #[inject("string")]
+    value: i32,
+}
+
+fn main() {}